@@ -1,40 +1,260 @@
 use std::fmt;
+use std::sync::Arc;
 use crate::types::*;
 
 // TODO:
 // give Nodes a children attribute, extend BinaryOperator from that
 // see Rust implementation by Ruslan Spivak: https://github.com/rspivak/lsbasi/blob/master/part7/rust/spi/src/main.rs
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AST {
     pub root: Node
 }
 
 impl fmt::Display for AST {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            _ => f.write_str(&format!("{:?}", self))
-        }
+        write!(f, "{:?}", self)
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BinaryOperator {
     pub left: Node,
-    pub token: Token,
-    pub right: Node
+    pub token: Spanned<Token>,
+    pub right: Node,
+    // type of the result, `None` until `semantic::check` inferred it
+    pub type_spec: Option<Type>
 }
 
-#[derive(Debug, Eq, PartialEq)]
-pub enum Node {
-    Token(Token),
-    BinaryOperator(Box<BinaryOperator>)
+// prefix `+`, `-` or `NOT` applied to `expr`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnaryOperator {
+    pub token: Spanned<Token>,
+    pub expr: Node
 }
 
-impl fmt::Display for Node {
+// `name := expr`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assign {
+    pub name: Spanned<String>,
+    pub token: Spanned<Token>,
+    pub expr: Node
+}
+
+// `PROGRAM name; block.`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub token: Spanned<Token>,
+    pub name: Spanned<String>,
+    pub block: Node
+}
+
+// declarations followed by the compound statement using them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub declarations: Vec<Node>,
+    pub compound_statement: Node
+}
+
+// `name : type_spec`, `VAR a, b : INTEGER` declares `a` and `b` separately
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDecl {
+    pub name: Spanned<String>,
+    pub type_spec: Spanned<Type>
+}
+
+// `PROCEDURE name(params); block;` or `FUNCTION name(params) : return_type; block;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcedureDecl {
+    pub token: Spanned<Token>,
+    pub name: Spanned<String>,
+    pub params: Vec<Param>,
+    // `None` for procedures, functions return the value last assigned to their name
+    pub return_type: Option<Spanned<Type>>,
+    pub block: Node
+}
+
+impl ProcedureDecl {
+    pub fn is_function(&self) -> bool {
+        self.return_type.is_some()
+    }
+}
+
+// formal parameter, `PROCEDURE p(a, b : INTEGER)` has one for `a` and one for `b`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Spanned<String>,
+    pub type_spec: Spanned<Type>
+}
+
+// `name(args)`, the parentheses are optional without arguments in statements;
+// function calls in expressions always need them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcedureCall {
+    pub name: Spanned<String>,
+    pub args: Vec<Node>,
+    // from the name up to the closing parenthesis
+    pub span: Span
+}
+
+// `IF condition THEN then_branch ELSE else_branch`, the ELSE part is optional
+#[derive(Debug, Clone, PartialEq)]
+pub struct If {
+    pub token: Spanned<Token>,
+    pub condition: Node,
+    pub then_branch: Node,
+    pub else_branch: Option<Node>
+}
+
+// `WHILE condition DO body`
+#[derive(Debug, Clone, PartialEq)]
+pub struct While {
+    pub token: Spanned<Token>,
+    pub condition: Node,
+    pub body: Node
+}
+
+// `REPEAT body UNTIL condition`, the body runs at least once
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repeat {
+    pub token: Spanned<Token>,
+    pub body: Vec<Node>,
+    pub condition: Node
+}
+
+// `FOR variable := start TO end DO body`, counting down with DOWNTO; the
+// bounds are evaluated once and the body must not assign to the variable
+#[derive(Debug, Clone, PartialEq)]
+pub struct For {
+    pub token: Spanned<Token>,
+    pub variable: Spanned<String>,
+    pub start: Node,
+    // TO or DOWNTO
+    pub direction: Spanned<Token>,
+    pub end: Node,
+    pub body: Node
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Type {
+    Integer,
+    Real,
+    Boolean,
+    String
+}
+
+impl Type {
+    pub fn is_numeric(self) -> bool {
+        matches!(self, Type::Integer | Type::Real)
+    }
+}
+
+// spelled like the type in a declaration
+impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            _ => f.write_str(&format!("{:?}", self))
+            Type::Integer => f.write_str("INTEGER"),
+            Type::Real => f.write_str("REAL"),
+            Type::Boolean => f.write_str("BOOLEAN"),
+            Type::String => f.write_str("STRING")
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Token(Spanned<Token>),
+    BinaryOperator(Box<BinaryOperator>),
+    UnaryOperator(Box<UnaryOperator>),
+    Assign(Box<Assign>),
+    // reference to the variable with the given name
+    Var(Spanned<String>),
+    Program(Box<Program>),
+    Block(Box<Block>),
+    VarDecl(Box<VarDecl>),
+    // shared with the call stack, which keeps declarations while they are in scope
+    ProcedureDecl(Arc<ProcedureDecl>),
+    ProcedureCall(Box<ProcedureCall>),
+    If(Box<If>),
+    While(Box<While>),
+    Repeat(Box<Repeat>),
+    For(Box<For>),
+    // statements between BEGIN and END, the span covers both keywords
+    Compound(Spanned<Vec<Node>>),
+    // empty statement, e.g. before END in `BEGIN x := 1; END`
+    NoOp(Span)
+}
+
+impl Node {
+    // the literal `2147483648`, which only fits into an Integer once negated
+    pub fn is_min_integer_magnitude(&self) -> bool {
+        matches!(self, Node::Token(Spanned { value: Token::Number(Number::MinIntegerMagnitude), .. }))
+    }
+
+    // source range covered by the node and all of its children
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Token(token) => token.span,
+            Node::BinaryOperator(node) => node.left.span().to(node.right.span()),
+            Node::UnaryOperator(node) => node.token.span.to(node.expr.span()),
+            Node::Assign(node) => node.name.span.to(node.expr.span()),
+            Node::Var(name) => name.span,
+            Node::Program(node) => node.token.span.to(node.block.span()),
+            Node::Block(node) => match node.declarations.first() {
+                Some(declaration) => declaration.span().to(node.compound_statement.span()),
+                None => node.compound_statement.span()
+            },
+            Node::VarDecl(node) => node.name.span.to(node.type_spec.span),
+            Node::ProcedureDecl(node) => node.token.span.to(node.block.span()),
+            Node::ProcedureCall(node) => node.span,
+            Node::If(node) => match &node.else_branch {
+                Some(else_branch) => node.token.span.to(else_branch.span()),
+                None => node.token.span.to(node.then_branch.span())
+            },
+            Node::While(node) => node.token.span.to(node.body.span()),
+            Node::Repeat(node) => node.token.span.to(node.condition.span()),
+            Node::For(node) => node.token.span.to(node.body.span()),
+            Node::Compound(node) => node.span,
+            Node::NoOp(span) => *span
+        }
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[test]
+fn binary_operator_span_covers_both_operands() {
+    let node = Node::BinaryOperator(Box::new(BinaryOperator {
+        left: Node::Token(Spanned::new(Token::Number(Number::Integer(1)), Span::new(0, 1, 1, 1))),
+        token: Spanned::new(Token::Operator(Operator::Add), Span::new(2, 3, 1, 3)),
+        right: Node::Token(Spanned::new(Token::Number(Number::Integer(20)), Span::new(4, 6, 1, 5))),
+        type_spec: None
+    }));
+
+    assert_eq!(node.span(), Span::new(0, 6, 1, 1));
+}
+
+#[test]
+fn unary_operator_span_starts_at_the_operator() {
+    let node = Node::UnaryOperator(Box::new(UnaryOperator {
+        token: Spanned::new(Token::Operator(Operator::Sub), Span::new(0, 1, 1, 1)),
+        expr: Node::Token(Spanned::new(Token::Number(Number::Integer(3)), Span::new(1, 2, 1, 2)))
+    }));
+
+    assert_eq!(node.span(), Span::new(0, 2, 1, 1));
+}
+
+#[test]
+fn assign_span_starts_at_the_variable() {
+    let node = Node::Assign(Box::new(Assign {
+        name: Spanned::new("x".to_string(), Span::new(0, 1, 1, 1)),
+        token: Spanned::new(Token::ASSIGN, Span::new(2, 4, 1, 3)),
+        expr: Node::Token(Spanned::new(Token::Number(Number::Integer(3)), Span::new(5, 6, 1, 6)))
+    }));
+
+    assert_eq!(node.span(), Span::new(0, 6, 1, 1));
+}
@@ -0,0 +1,148 @@
+use std::convert::TryFrom;
+
+use crate::ast::Type;
+use crate::value::Value;
+
+// a function every program can call without declaring it, procedures declared
+// with the same name shadow it
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub params: &'static [Type],
+    pub return_type: Type,
+    // only ever called with arguments of the types in `params`
+    function: fn(&[Value]) -> Value
+}
+
+impl Builtin {
+    pub fn call(&self, args: &[Value]) -> Value {
+        (self.function)(args)
+    }
+}
+
+// a name only ever refers to one built-in
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Builtin) -> bool {
+        self.name == other.name
+    }
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin { name: "length", params: &[Type::String], return_type: Type::Integer, function: length },
+    Builtin { name: "copy", params: &[Type::String, Type::Integer, Type::Integer], return_type: Type::String, function: copy },
+    Builtin { name: "pos", params: &[Type::String, Type::String], return_type: Type::Integer, function: pos },
+    Builtin { name: "upcase", params: &[Type::String], return_type: Type::String, function: upcase }
+];
+
+// names are case-insensitive, like keywords
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name.eq_ignore_ascii_case(name))
+}
+
+// parameter types, e.g. "STRING, INTEGER"
+pub fn signature(params: &[Type]) -> String {
+    params.iter().map(Type::to_string).collect::<Vec<String>>().join(", ")
+}
+
+fn string(value: &Value) -> &str {
+    match value {
+        Value::String(value) => value,
+        value => unreachable!("{} is not a STRING", value)
+    }
+}
+
+// BigIntegers saturate, no string is that long anyway
+fn integer(value: &Value) -> i32 {
+    match value {
+        Value::Integer(value) => *value,
+        value if value.to_real() > 0.0 => i32::MAX,
+        _ => i32::MIN
+    }
+}
+
+// strings are measured and indexed in characters, not bytes
+fn length(args: &[Value]) -> Value {
+    Value::Integer(i32::try_from(string(&args[0]).chars().count()).unwrap_or(i32::MAX))
+}
+
+// `copy(s, index, count)`: up to `count` characters starting at the 1-based
+// `index`, an index before the first character starts at the first one
+fn copy(args: &[Value]) -> Value {
+    let skip = usize::try_from(integer(&args[1]).saturating_sub(1)).unwrap_or(0);
+    let count = usize::try_from(integer(&args[2])).unwrap_or(0);
+
+    Value::String(string(&args[0]).chars().skip(skip).take(count).collect())
+}
+
+// `pos(substring, s)`: 1-based index of the first occurrence, 0 if there is none
+fn pos(args: &[Value]) -> Value {
+    let (substring, string) = (string(&args[0]), string(&args[1]));
+
+    let index = match string.find(substring) {
+        Some(start) if !substring.is_empty() => string[..start].chars().count() + 1,
+        _ => 0
+    };
+
+    Value::Integer(i32::try_from(index).unwrap_or(i32::MAX))
+}
+
+// one character at a time so the length stays the same, characters without a
+// single uppercase character like 'ß' stay as they are
+fn upcase(args: &[Value]) -> Value {
+    let upper = |character: char| {
+        let mut upper = character.to_uppercase();
+
+        match (upper.next(), upper.next()) {
+            (Some(upper), None) => upper,
+            _ => character
+        }
+    };
+
+    Value::String(string(&args[0]).chars().map(upper).collect())
+}
+
+#[cfg(test)]
+fn call(name: &str, args: &[Value]) -> Value {
+    builtin(name).unwrap().call(args)
+}
+
+#[cfg(test)]
+fn text(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+#[test]
+fn length_counts_characters() {
+    assert_eq!(call("length", &[text("")]), Value::Integer(0));
+    assert_eq!(call("length", &[text("größe")]), Value::Integer(5));
+}
+
+#[test]
+fn copy_clamps_to_the_string() {
+    assert_eq!(call("copy", &[text("hello"), Value::Integer(2), Value::Integer(3)]), text("ell"));
+    assert_eq!(call("copy", &[text("hello"), Value::Integer(4), Value::Integer(10)]), text("lo"));
+    assert_eq!(call("copy", &[text("hello"), Value::Integer(0), Value::Integer(2)]), text("he"));
+    assert_eq!(call("copy", &[text("hello"), Value::Integer(9), Value::Integer(2)]), text(""));
+    assert_eq!(call("copy", &[text("hello"), Value::Integer(1), Value::Integer(-1)]), text(""));
+}
+
+#[test]
+fn pos_returns_the_first_occurrence_or_zero() {
+    assert_eq!(call("pos", &[text("lo"), text("hello, lo")]), Value::Integer(4));
+    assert_eq!(call("pos", &[text("ö"), text("größe")]), Value::Integer(3));
+    assert_eq!(call("pos", &[text("x"), text("hello")]), Value::Integer(0));
+    assert_eq!(call("pos", &[text(""), text("hello")]), Value::Integer(0));
+}
+
+#[test]
+fn upcase_converts_every_character() {
+    assert_eq!(call("upcase", &[text("Hello, größe")]), text("HELLO, GRÖßE"));
+    assert_eq!(call("length", &[call("upcase", &[text("straße")])]), Value::Integer(6));
+}
+
+#[test]
+fn builtin_names_are_case_insensitive() {
+    assert_eq!(builtin("LENGTH"), builtin("length"));
+    assert_eq!(builtin("UpCase").map(|builtin| builtin.name), Some("upcase"));
+    assert_eq!(builtin("lengths"), None);
+}
@@ -0,0 +1,173 @@
+use crate::error::*;
+use crate::types::Span;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ColorMode {
+    Plain,
+    Ansi
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+fn paint(color: ColorMode, style: &str, text: &str) -> String {
+    match color {
+        ColorMode::Plain => text.to_string(),
+        ColorMode::Ansi => format!("{}{}{}", style, text, RESET)
+    }
+}
+
+struct Marker<'a> {
+    span: Span,
+    message: &'a str,
+    primary: bool
+}
+
+// byte offset of the first character of every line in `source`
+fn line_starts(source: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+
+    for (index, c) in source.char_indices() {
+        if c == '\n' {
+            starts.push(index + 1);
+        }
+    }
+
+    starts
+}
+
+fn line_text<'a>(source: &'a str, starts: &[usize], line: usize) -> (usize, &'a str) {
+    match starts.get(line - 1) {
+        Some(&start) => {
+            let end = starts.get(line).map_or(source.len(), |&next| next);
+            (start, source[start..end].trim_end_matches(&['\n', '\r'][..]))
+        },
+        None => (source.len(), "")
+    }
+}
+
+fn underline(text: &str, line_start: usize, marker: &Marker) -> (String, String) {
+    let offset = clamp_to_char_boundary(text, marker.span.start.saturating_sub(line_start));
+    let end = clamp_to_char_boundary(text, marker.span.end.saturating_sub(line_start));
+
+    // keep tabs so the markers line up with the source line above
+    let indent: String = text[..offset].chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = std::cmp::max(text[offset..std::cmp::max(offset, end)].chars().count(), 1);
+    let mark = if marker.primary { "^" } else { "-" };
+
+    (indent, mark.repeat(width))
+}
+
+fn clamp_to_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = std::cmp::min(index, text.len());
+
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
+
+// renders `error` with excerpts of the `source` it was reported for, marking the
+// primary span with `^^^` and every secondary label with `---`
+pub fn render(error: &Error, source: &str, color: ColorMode) -> String {
+    let mut markers = vec![Marker { span: error.span, message: &error.message, primary: true }];
+    markers.extend(error.labels.iter().map(|label| Marker {
+        span: label.span,
+        message: &label.message,
+        primary: false
+    }));
+    markers.sort_by_key(|marker| (marker.span.line, marker.span.column));
+
+    let mut lines: Vec<usize> = markers.iter().map(|marker| marker.span.line).collect();
+    lines.dedup();
+
+    let width = lines.iter().max().map_or(1, |line| line.to_string().len());
+    let padding = " ".repeat(width);
+    let gutter = paint(color, BLUE, &format!("{} |", padding));
+    let starts = line_starts(source);
+    let primary_style = if error.is_warning() { YELLOW } else { RED };
+
+    let mut output = format!(
+        "{}: {}\n{}{} {}\n{}\n",
+        paint(color, primary_style, &error.title()),
+        paint(color, BOLD, &error.message),
+        padding,
+        paint(color, BLUE, "-->"),
+        error.span,
+        gutter
+    );
+
+    for line in lines {
+        let (line_start, text) = line_text(source, &starts, line);
+        let number = paint(color, BLUE, &format!("{:>width$} |", line, width = width));
+        output.push_str(&format!("{} {}\n", number, text));
+
+        for marker in markers.iter().filter(|marker| marker.span.line == line) {
+            let (indent, marks) = underline(text, line_start, marker);
+            let style = if marker.primary { primary_style } else { BLUE };
+
+            output.push_str(&format!(
+                "{} {}{}\n",
+                gutter,
+                indent,
+                paint(color, style, &format!("{} {}", marks, marker.message))
+            ));
+        }
+    }
+
+    for note in &error.notes {
+        let equals = paint(color, BLUE, &format!("{} =", padding));
+        output.push_str(&format!("{} {}\n", equals, paint(color, BOLD, &format!("note: {}", note))));
+    }
+
+    output
+}
+
+#[test]
+fn render_underlines_primary_span_and_labels() {
+    let source = "1 + (2";
+    let error = Error::syntax("expected RPAREN, found EOF", Span::new(6, 6, 1, 7))
+        .with_label(Span::new(4, 5, 1, 5), "unclosed `(` opened here");
+
+    assert_eq!(render(&error, source, ColorMode::Plain), [
+        "Syntax Error: expected RPAREN, found EOF",
+        " --> 1:7",
+        "  |",
+        "1 | 1 + (2",
+        "  |     - unclosed `(` opened here",
+        "  |       ^ expected RPAREN, found EOF",
+        ""
+    ].join("\n"));
+}
+
+#[test]
+fn render_underlines_the_whole_span_and_prints_notes() {
+    let source = "12 +\n345 )";
+    let error = Error::runtime("integer overflow", Span::new(5, 8, 2, 1))
+        .with_note("integers are 32 bits wide");
+
+    assert_eq!(render(&error, source, ColorMode::Plain), [
+        "Runtime Error: integer overflow",
+        " --> 2:1",
+        "  |",
+        "2 | 345 )",
+        "  | ^^^ integer overflow",
+        "  = note: integers are 32 bits wide",
+        ""
+    ].join("\n"));
+}
+
+#[test]
+fn render_in_ansi_mode_colors_the_output() {
+    let error = Error::lexical("unexpected character \"$\"", Span::new(0, 1, 1, 1));
+    let output = render(&error, "$", ColorMode::Ansi);
+
+    assert!(output.starts_with("\x1b[1;31mLexical Error\x1b[0m"));
+    assert!(output.contains("\x1b[1;31m^ unexpected character \"$\"\x1b[0m"));
+}
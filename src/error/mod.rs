@@ -0,0 +1,138 @@
+use std::fmt;
+use crate::types::Span;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ErrorKind {
+    Lexical,
+    Syntax,
+    // found by `semantic::analyze` before the program runs
+    Semantic,
+    Runtime,
+    // the interpreter ran out of steps or time, see `Limits::max_steps`
+    Budget
+}
+
+impl ErrorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Lexical => "Lexical",
+            ErrorKind::Syntax => "Syntax",
+            ErrorKind::Semantic => "Semantic",
+            ErrorKind::Runtime => "Runtime",
+            ErrorKind::Budget => "Budget"
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} Error", self.name())
+    }
+}
+
+// warnings point out suspicious code without stopping the program from running
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+// secondary location attached to an error, e.g. where an unclosed `(` was opened
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>, span: Span) -> Error {
+        Error {
+            kind,
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new()
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Error {
+        self.labels.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Error {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn into_warning(mut self) -> Error {
+        self.severity = Severity::Warning;
+        self
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+
+    // e.g. "Syntax Error" or "Semantic Warning"
+    pub fn title(&self) -> String {
+        match self.severity {
+            Severity::Error => self.kind.to_string(),
+            Severity::Warning => format!("{} Warning", self.kind.name())
+        }
+    }
+
+    pub fn lexical(message: impl Into<String>, span: Span) -> Error {
+        Error::new(ErrorKind::Lexical, message, span)
+    }
+
+    pub fn syntax(message: impl Into<String>, span: Span) -> Error {
+        Error::new(ErrorKind::Syntax, message, span)
+    }
+
+    pub fn semantic(message: impl Into<String>, span: Span) -> Error {
+        Error::new(ErrorKind::Semantic, message, span)
+    }
+
+    pub fn runtime(message: impl Into<String>, span: Span) -> Error {
+        Error::new(ErrorKind::Runtime, message, span)
+    }
+
+    pub fn budget(message: impl Into<String>, span: Span) -> Error {
+        Error::new(ErrorKind::Budget, message, span)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} at {}", self.title(), self.message, self.span)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[test]
+fn error_display_includes_kind_message_and_location() {
+    let error = Error::syntax("expected RPAREN, found EOF", Span::new(4, 4, 1, 5));
+
+    assert_eq!(format!("{}", error), "Syntax Error: expected RPAREN, found EOF at 1:5");
+}
+
+#[test]
+fn warnings_are_titled_as_warnings() {
+    let warning = Error::semantic("variable \"x\" shadows a variable", Span::new(4, 5, 1, 5)).into_warning();
+
+    assert!(warning.is_warning());
+    assert_eq!(warning.to_string(), "Semantic Warning: variable \"x\" shadows a variable at 1:5");
+}
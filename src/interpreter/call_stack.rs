@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast::ProcedureDecl;
+use crate::value::Value;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RecordKind {
+    Program,
+    Procedure,
+    Function
+}
+
+impl fmt::Display for RecordKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordKind::Program => f.write_str("PROGRAM"),
+            RecordKind::Procedure => f.write_str("PROCEDURE"),
+            RecordKind::Function => f.write_str("FUNCTION")
+        }
+    }
+}
+
+// memory of one running program or procedure call
+#[derive(Debug, Clone)]
+pub struct ActivationRecord {
+    pub name: String,
+    pub kind: RecordKind,
+    pub nesting_level: usize,
+    // index of the record of the lexically enclosing scope on the call stack,
+    // `None` for the program itself
+    pub access_link: Option<usize>,
+    // changed through `CallStack::insert` once the record is on the stack, which
+    // keeps the stack's totals up to date
+    pub members: BTreeMap<String, Value>,
+    // procedures declared in this scope, shared with the records of their calls
+    pub procedures: HashMap<String, Arc<ProcedureDecl>>
+}
+
+impl ActivationRecord {
+    pub fn new(name: impl Into<String>, kind: RecordKind, nesting_level: usize, access_link: Option<usize>) -> ActivationRecord {
+        ActivationRecord {
+            name: name.into(),
+            kind,
+            nesting_level,
+            access_link,
+            members: BTreeMap::new(),
+            procedures: HashMap::new()
+        }
+    }
+
+    // `Value::size` of all variables and parameters
+    pub fn value_size(&self) -> usize {
+        self.members.values().map(Value::size).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CallStack {
+    // the program's record first, the running procedure's last
+    records: Vec<ActivationRecord>,
+    // the totals over all records, so the limits on them take no scan
+    variables: usize,
+    value_size: usize
+}
+
+impl CallStack {
+    pub fn new(program: ActivationRecord) -> CallStack {
+        let mut stack = CallStack { records: Vec::new(), variables: 0, value_size: 0 };
+        stack.push(program);
+
+        stack
+    }
+
+    pub fn push(&mut self, record: ActivationRecord) {
+        self.variables += record.members.len();
+        self.value_size += record.value_size();
+        self.records.push(record);
+    }
+
+    // the program's record is never popped
+    pub fn pop(&mut self) -> Option<ActivationRecord> {
+        if self.records.len() == 1 {
+            return None
+        }
+
+        let record = self.records.pop()?;
+        self.variables -= record.members.len();
+        self.value_size -= record.value_size();
+
+        Some(record)
+    }
+
+    // sets the variable `name` of the record at `index`, returning the value it replaces
+    pub fn insert(&mut self, index: usize, name: impl Into<String>, value: Value) -> Option<Value> {
+        self.value_size += value.size();
+
+        let previous = self.records[index].members.insert(name.into(), value);
+        match &previous {
+            Some(previous) => self.value_size -= previous.size(),
+            None => self.variables += 1
+        }
+
+        previous
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    // variables and parameters over all records
+    pub fn variables(&self) -> usize {
+        self.variables
+    }
+
+    // `Value::size` of all variables and parameters over all records
+    pub fn value_size(&self) -> usize {
+        self.value_size
+    }
+
+    pub fn global(&self) -> &ActivationRecord {
+        &self.records[0]
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.records.len() - 1
+    }
+
+    pub fn current(&self) -> &ActivationRecord {
+        &self.records[self.current_index()]
+    }
+
+    pub fn current_mut(&mut self) -> &mut ActivationRecord {
+        let index = self.current_index();
+
+        &mut self.records[index]
+    }
+
+    pub fn get(&self, index: usize) -> &ActivationRecord {
+        &self.records[index]
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> &mut ActivationRecord {
+        &mut self.records[index]
+    }
+
+    // index of the innermost record visible from the running one that satisfies
+    // `predicate`, following the access links outwards
+    pub fn resolve(&self, predicate: impl Fn(&ActivationRecord) -> bool) -> Option<usize> {
+        let mut index = Some(self.current_index());
+
+        while let Some(current) = index {
+            if predicate(&self.records[current]) {
+                return Some(current)
+            }
+
+            index = self.records[current].access_link;
+        }
+
+        None
+    }
+
+    // index of the record holding the variable `name` as seen from the running record
+    pub fn resolve_variable(&self, name: &str) -> Option<usize> {
+        self.resolve(|record| record.members.contains_key(name))
+    }
+
+    pub fn resolve_procedure(&self, name: &str) -> Option<(usize, Arc<ProcedureDecl>)> {
+        let index = self.resolve(|record| record.procedures.contains_key(name))?;
+
+        Some((index, Arc::clone(&self.records[index].procedures[name])))
+    }
+}
+
+impl Default for CallStack {
+    // a stack for evaluating single statements outside of any program
+    fn default() -> CallStack {
+        CallStack::new(ActivationRecord::new("global", RecordKind::Program, 1, None))
+    }
+}
+
+// the running record first, e.g.
+//
+//     CALL STACK
+//     2: PROCEDURE alpha
+//        a : 10
+//     1: PROGRAM main
+//        x : 3
+impl fmt::Display for CallStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "CALL STACK")?;
+
+        for record in self.records.iter().rev() {
+            writeln!(f, "{}: {} {}", record.nesting_level, record.kind, record.name)?;
+
+            let width = record.members.keys().map(|name| name.chars().count()).max().unwrap_or(0);
+
+            for (name, value) in &record.members {
+                writeln!(f, "   {:width$} : {}", name, value, width = width)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn resolve_variable_follows_access_links_past_unrelated_records() {
+    let mut stack = CallStack::default();
+    stack.insert(0, "x", Value::Integer(1));
+
+    let mut caller = ActivationRecord::new("a", RecordKind::Procedure, 2, Some(0));
+    caller.members.insert("y".to_string(), Value::Integer(2));
+    stack.push(caller);
+    stack.push(ActivationRecord::new("b", RecordKind::Procedure, 2, Some(0)));
+
+    assert_eq!(stack.resolve_variable("x"), Some(0));
+    assert_eq!(stack.resolve_variable("y"), None);
+}
+
+#[test]
+fn pop_keeps_the_program_record() {
+    let mut stack = CallStack::default();
+    stack.push(ActivationRecord::new("a", RecordKind::Procedure, 2, Some(0)));
+
+    assert_eq!(stack.pop().map(|record| record.name), Some("a".to_string()));
+    assert!(stack.pop().is_none());
+    assert_eq!(stack.len(), 1);
+}
+
+#[test]
+fn display_dumps_the_running_record_first() {
+    let mut stack = CallStack::default();
+    stack.current_mut().members.insert("x".to_string(), Value::Integer(3));
+
+    let mut record = ActivationRecord::new("alpha", RecordKind::Procedure, 2, Some(0));
+    record.members.insert("a".to_string(), Value::Integer(10));
+    record.members.insert("bb".to_string(), Value::Real(2.5));
+    stack.push(record);
+
+    assert_eq!(stack.to_string(), [
+        "CALL STACK",
+        "2: PROCEDURE alpha",
+        "   a  : 10",
+        "   bb : 2.5",
+        "1: PROGRAM global",
+        "   x : 3",
+        ""
+    ].join("\n"));
+}
+
+#[test]
+fn totals_follow_inserts_and_records_entering_and_leaving() {
+    let mut stack = CallStack::default();
+    stack.insert(0, "s", Value::String("abc".to_string()));
+    stack.insert(0, "n", Value::Integer(1));
+
+    let mut record = ActivationRecord::new("a", RecordKind::Procedure, 2, Some(0));
+    record.members.insert("t".to_string(), Value::String("de".to_string()));
+    stack.push(record);
+    assert_eq!((stack.variables(), stack.value_size()), (3, 5));
+
+    assert_eq!(stack.insert(0, "s", Value::String("f".to_string())), Some(Value::String("abc".to_string())));
+    assert_eq!((stack.variables(), stack.value_size()), (3, 3));
+
+    stack.pop();
+    assert_eq!((stack.variables(), stack.value_size()), (2, 1));
+}
@@ -1,141 +1,1784 @@
 use crate::types::*;
 use crate::ast::*;
+use crate::builtins::*;
+use crate::error::*;
+use crate::value::*;
 
-struct Interpreter {}
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::panic;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod call_stack;
+
+pub use self::call_stack::{ActivationRecord, CallStack, RecordKind};
+
+// what integer arithmetic does when the result does not fit into an Integer,
+// exact fractions that no longer fit continue as Reals in every mode but `Checked`
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum OverflowMode {
+    // report a runtime error
+    #[cfg_attr(not(feature = "bignum"), default)]
+    Checked,
+    // wrap around at the boundary of the type (two's complement)
+    Wrapping,
+    // clamp to the smallest or largest Integer
+    Saturating,
+    // continue with an unbounded BigInteger
+    #[cfg(feature = "bignum")]
+    #[default]
+    Promote
+}
+
+// what `/` returns for two exact (Integer or Rational) operands
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum DivisionMode {
+    #[default]
+    Real,
+    // an exact Rational, e.g. `1 / 3 + 1 / 6 = 1/2`
+    Exact
+}
+
+// deep enough for any reasonable recursion
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+// how many steps pass between two looks at the clock for `Limits::time_limit`
+const STEPS_PER_CLOCK_CHECK: u64 = 1024;
+
+// stack of the thread `Interpreter::interpret` runs on, enough to reach
+// `DEFAULT_MAX_CALL_DEPTH` although unoptimized builds take 25 to 50 KiB per
+// call; deeper calls than fit into it fail like calls beyond `Limits::max_call_depth`
+pub const STACK_SIZE: usize = 128 * 1024 * 1024;
+
+// part of `STACK_SIZE` no call may start in, it is left for the statements and
+// expressions of the innermost call
+const STACK_RESERVE: usize = STACK_SIZE / 4;
+
+// resources a single call to `Interpreter::interpret` may use, so untrusted
+// programs cannot hang or exhaust the process; `None` is unlimited
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Limits {
+    // calls nested deeper than this are reported as a runtime error
+    pub max_call_depth: usize,
+    // levels expressions, statements and declarations may nest, checked by
+    // `parser::parse_with_max_depth` before anything recurses over the tree
+    pub max_nesting_depth: usize,
+    // steps, one per statement or expression node, before failing with an
+    // `ErrorKind::Budget` error
+    pub max_steps: Option<u64>,
+    // like `max_steps`, but for the time running may take
+    pub time_limit: Option<Duration>,
+    // variables and parameters alive at the same time, over all running calls
+    pub max_variables: Option<usize>,
+    // bytes a single value may take up, e.g. a string or the digits of a BigInteger
+    pub max_value_size: Option<usize>,
+    // like `max_value_size`, but for all values alive at the same time: the
+    // variables and parameters of every running call and the value just computed
+    pub max_total_value_size: Option<usize>
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_nesting_depth: crate::parser::DEFAULT_MAX_NESTING_DEPTH,
+            max_steps: None,
+            time_limit: None,
+            max_variables: None,
+            max_value_size: None,
+            max_total_value_size: None
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    pub overflow_mode: OverflowMode,
+    pub division_mode: DivisionMode,
+    // dump the call stack to stderr whenever a procedure is entered or left
+    pub trace_calls: bool,
+    pub limits: Limits,
+    steps: u64,
+    deadline: Option<Instant>,
+    // variables outside of a program stay on the stack across calls to `interpret`
+    pub call_stack: CallStack,
+    // record index and name of the variables of the running FOR loops
+    loop_variables: Vec<(usize, String)>,
+    // `stack_position` when `interpret` was called
+    stack_base: usize
+}
+
+// INTEGER values stored in REAL variables or parameters become Reals
+fn convert(type_spec: Type, value: Value) -> Value {
+    match type_spec {
+        Type::Real if value.is_integer() => Value::Real(value.to_real()),
+        _ => value
+    }
+}
 
 impl Interpreter {
-    fn visit(&self, node: &Node) -> i32 {
+    pub fn new(overflow_mode: OverflowMode) -> Interpreter {
+        Interpreter { overflow_mode, ..Interpreter::default() }
+    }
+
+    // the value of an expression or assignment, `None` for programs and every
+    // other statement
+    //
+    // runs on a thread of its own with a stack of `STACK_SIZE`, whatever thread
+    // it is called on
+    pub fn interpret(&mut self, ast: &AST) -> Result<Option<Value>, Error> {
+        thread::scope(|scope| {
+            let thread = thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, || self.run(ast))
+                .expect("failed to spawn the interpreter thread");
+
+            thread.join().unwrap_or_else(|panic| panic::resume_unwind(panic))
+        })
+    }
+
+    fn run(&mut self, ast: &AST) -> Result<Option<Value>, Error> {
+        self.steps = 0;
+        self.stack_base = stack_position();
+        self.deadline = self.limits.time_limit.map(|time_limit| Instant::now() + time_limit);
+
+        match &ast.root {
+            Node::Program(_) | Node::Compound(_) | Node::NoOp(_) => self.execute(&ast.root).map(|_| None),
+            node => self.visit(node).map(Some)
+        }
+    }
+
+    fn trace(&self, event: &str) {
+        if self.trace_calls {
+            let record = self.call_stack.current();
+
+            eprint!("{}: {} {}\n{}", event, record.kind, record.name, self.call_stack);
+        }
+    }
+
+    // steps taken by the last call to `interpret`
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // counts one step for running `node`, failing once the budget is used up
+    fn step(&mut self, node: &Node) -> Result<(), Error> {
+        self.steps += 1;
+
+        if let Some(max_steps) = self.limits.max_steps.filter(|&max_steps| self.steps > max_steps) {
+            return Err(
+                Error::budget(format!("step budget of {} exhausted", max_steps), node.span())
+                    .with_note("is a loop or recursion missing its exit condition?")
+            )
+        }
+
+        if let (Some(deadline), Some(time_limit)) = (self.deadline, self.limits.time_limit) {
+            if self.steps.is_multiple_of(STEPS_PER_CLOCK_CHECK) && Instant::now() >= deadline {
+                return Err(
+                    Error::budget(format!("time limit of {:?} exceeded", time_limit), node.span())
+                        .with_note(format!("after {} steps", self.steps))
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    // fails if `count` more variables would exceed `Limits::max_variables`
+    fn reserve_variables(&self, count: usize, span: Span) -> Result<(), Error> {
+        match self.limits.max_variables {
+            Some(max_variables) if self.call_stack.variables() + count > max_variables => Err(
+                Error::runtime(format!("limit of {} live variables exceeded", max_variables), span)
+                    .with_note("the variables and parameters of every running call count towards the limit")
+            ),
+            _ => Ok(())
+        }
+    }
+
+    // fails if a new value of `size` bytes exceeds `Limits::max_value_size`, or
+    // `Limits::max_total_value_size` together with the values of all variables
+    fn check_value_size(&self, size: usize, span: Span) -> Result<(), Error> {
+        match self.limits.max_value_size {
+            Some(max_value_size) if size > max_value_size => Err(Error::runtime(
+                format!("value of {} bytes exceeds the limit of {} bytes", size, max_value_size),
+                span
+            )),
+            _ => self.check_total_value_size(size, 0, span)
+        }
+    }
+
+    // like `check_value_size` for the total only, for a value stored in place of
+    // one of `replaced` bytes
+    fn check_total_value_size(&self, size: usize, replaced: usize, span: Span) -> Result<(), Error> {
+        match self.limits.max_total_value_size {
+            // a value no bigger than the one it replaces keeps within the limit
+            Some(max_total_value_size) if size > replaced => {
+                let total = (self.call_stack.value_size() - replaced).saturating_add(size);
+
+                if total <= max_total_value_size {
+                    return Ok(())
+                }
+
+                Err(
+                    Error::runtime(format!("values of {} bytes exceed the total limit of {} bytes", total, max_total_value_size), span)
+                        .with_note("the variables and parameters of every running call count towards the limit")
+                )
+            },
+            _ => Ok(())
+        }
+    }
+
+    // checks the size of the result before computing it, `2 ** 2000000000`
+    // would take a while to find out otherwise
+    #[cfg(feature = "bignum")]
+    fn big_integer_arithmetic(&self, operator: Operator, left: num_bigint::BigInt, right: num_bigint::BigInt, span: Span) -> Result<Value, Error> {
+        let size = usize::try_from(big_integer_bits(operator, &left, &right).div_ceil(8)).unwrap_or(usize::MAX);
+        self.check_value_size(size, span)?;
+
+        Ok(big_integer_arithmetic(operator, left, right))
+    }
+
+    fn execute(&mut self, node: &Node) -> Result<(), Error> {
+        self.step(node)?;
+
         match node {
-            Node::BinaryOperator(node) => {
-                let result: i32;
-                match node.token {
-                    Token::Operator(Operator::Add) => result = self.visit(&node.left) + self.visit(&node.right),
-                    Token::Operator(Operator::Sub) => result = self.visit(&node.left) - self.visit(&node.right),
-                    Token::Operator(Operator::Mul) => result = self.visit(&node.left) * self.visit(&node.right),
-                    Token::Operator(Operator::Div) => result = self.visit(&node.left) / self.visit(&node.right),
-                    _ => panic!(format!("AST Traversal Error: expected Operator, found {}.", node.token))
+            // every run of a program starts out with empty memory
+            Node::Program(node) => {
+                self.call_stack = CallStack::new(ActivationRecord::new(node.name.value.clone(), RecordKind::Program, 1, None));
+
+                self.execute(&node.block)
+            },
+            Node::Block(node) => {
+                for declaration in &node.declarations {
+                    self.execute(declaration)?;
+                }
+
+                self.execute(&node.compound_statement)
+            },
+            Node::ProcedureDecl(node) => {
+                self.call_stack.current_mut().procedures.insert(node.name.value.clone(), Arc::clone(node));
+
+                Ok(())
+            },
+            Node::ProcedureCall(node) => self.call(node).map(|_| ()),
+            Node::VarDecl(node) => {
+                let value = initial_value(node.type_spec.value);
+
+                self.reserve_variables(1, node.name.span)?;
+                self.check_total_value_size(value.size(), 0, node.name.span)?;
+                self.call_stack.insert(self.call_stack.current_index(), node.name.value.clone(), value);
+
+                Ok(())
+            },
+            // unlike a single assignment outside of a program, which defines the
+            // variable, assignments in programs need a declaration
+            Node::Assign(node) => {
+                let index = self.call_stack.resolve_variable(&node.name.value).ok_or_else(|| {
+                    Error::runtime(format!("variable \"{}\" is not declared", node.name.value), node.name.span)
+                        .with_note("declare it in a VAR section before the program's BEGIN")
+                })?;
+                // a procedure called from the loop's body can still reach the variable
+                if self.loop_variables.contains(&(index, node.name.value.clone())) {
+                    return Err(
+                        Error::runtime(format!("cannot assign to loop variable \"{}\"", node.name.value), node.name.span)
+                            .with_note("a FOR loop assigns its variable on every iteration")
+                    )
+                }
+
+                let value = self.visit(&node.expr)?;
+                let previous = &self.call_stack.get(index).members[&node.name.value];
+                let value = convert(previous.type_spec(), value);
+
+                self.check_total_value_size(value.size(), previous.size(), node.expr.span())?;
+                self.call_stack.insert(index, node.name.value.clone(), value);
+
+                Ok(())
+            },
+            Node::Compound(node) => {
+                for statement in &node.value {
+                    self.execute(statement)?;
                 }
-                result
+
+                Ok(())
             },
-            Node::Token(node) => {
-                let result: i32;
-                match node {
-                    Token::Number(Number::Integer(value)) => result = *value,
-                    _ => panic!(format!("AST Traversal Error: expected Number, found {}", node))
+            Node::If(node) => {
+                if self.condition(&node.condition)? {
+                    self.execute(&node.then_branch)
+                } else if let Some(else_branch) = &node.else_branch {
+                    self.execute(else_branch)
+                } else {
+                    Ok(())
+                }
+            },
+            Node::While(node) => {
+                let mut iteration = 1;
+
+                while self.condition(&node.condition).map_err(|error| in_iteration(error, &node.token, iteration, None))? {
+                    self.execute(&node.body).map_err(|error| in_iteration(error, &node.token, iteration, None))?;
+                    iteration += 1;
+                }
+
+                Ok(())
+            },
+            Node::Repeat(node) => {
+                for iteration in 1.. {
+                    let done = node.body.iter()
+                        .try_for_each(|statement| self.execute(statement))
+                        .and_then(|_| self.condition(&node.condition))
+                        .map_err(|error| in_iteration(error, &node.token, iteration, None))?;
+
+                    if done {
+                        break
+                    }
+                }
+
+                Ok(())
+            },
+            Node::For(node) => self.execute_for(node),
+            Node::NoOp(_) => Ok(()),
+            node => self.visit(node).map(|_| ())
+        }
+    }
+
+    fn execute_for(&mut self, node: &For) -> Result<(), Error> {
+        let name = &node.variable.value;
+        let index = self.call_stack.resolve_variable(name).ok_or_else(|| {
+            Error::runtime(format!("variable \"{}\" is not declared", name), node.variable.span)
+        })?;
+
+        if self.loop_variables.contains(&(index, name.clone())) {
+            return Err(Error::runtime(
+                format!("loop variable \"{}\" is already used by an enclosing FOR loop", name),
+                node.variable.span
+            ))
+        }
+
+        let start = self.loop_bound(&node.start)?;
+        let end = self.loop_bound(&node.end)?;
+        let downto = node.direction.value == Token::DOWNTO;
+
+        if (downto && start < end) || (!downto && start > end) {
+            return Ok(())
+        }
+
+        self.loop_variables.push((index, name.clone()));
+
+        let mut value = start;
+        let mut iteration = 1;
+        let result = loop {
+            self.call_stack.insert(index, name.clone(), Value::Integer(value));
+
+            if let Err(error) = self.execute(&node.body) {
+                break Err(in_iteration(error, &node.token, iteration, Some((name, value))))
+            }
+
+            // stopping at `end` rather than past it cannot overflow
+            if value == end {
+                break Ok(())
+            }
+
+            value = if downto { value - 1 } else { value + 1 };
+            iteration += 1;
+        };
+
+        self.loop_variables.pop();
+
+        result
+    }
+
+    fn loop_bound(&mut self, node: &Node) -> Result<i32, Error> {
+        match self.visit(node)? {
+            Value::Integer(value) => Ok(value),
+            value => Err(Error::runtime(format!("expected INTEGER bound, found {}", value.type_spec()), node.span()))
+        }
+    }
+
+    fn condition(&mut self, node: &Node) -> Result<bool, Error> {
+        match self.visit(node)? {
+            Value::Boolean(value) => Ok(value),
+            value => Err(Error::runtime(format!("expected BOOLEAN condition, found {}", value.type_spec()), node.span()))
+        }
+    }
+
+    // the value a function returns, `None` for procedures
+    fn call(&mut self, node: &ProcedureCall) -> Result<Option<Value>, Error> {
+        let (enclosing, procedure) = match self.call_stack.resolve_procedure(&node.name.value) {
+            Some(procedure) => procedure,
+            None => return match builtin(&node.name.value) {
+                Some(builtin) => self.call_builtin(builtin, node).map(Some),
+                None => Err(Error::runtime(format!("procedure \"{}\" is not declared", node.name.value), node.name.span))
+            }
+        };
+
+        // the program's record is not a call
+        if self.call_stack.len() > self.limits.max_call_depth {
+            return Err(
+                Error::runtime(format!("maximum call depth of {} exceeded", self.limits.max_call_depth), node.span)
+                    .with_label(procedure.name.span, format!("while calling \"{}\"", procedure.name.value))
+                    .with_note("check the recursion for a missing base case")
+            )
+        }
+
+        if stack_position().abs_diff(self.stack_base) > STACK_SIZE - STACK_RESERVE {
+            return Err(
+                Error::runtime(format!("maximum call depth exceeded after {} nested calls", self.call_stack.len() - 1), node.span)
+                    .with_label(procedure.name.span, format!("while calling \"{}\"", procedure.name.value))
+                    .with_note(format!("no more calls fit into the interpreter's stack of {} MiB", STACK_SIZE / 1024 / 1024))
+            )
+        }
+
+        if procedure.params.len() != node.args.len() {
+            return Err(
+                Error::runtime(
+                    format!("expected {} arguments, found {}", procedure.params.len(), node.args.len()),
+                    node.span
+                ).with_label(procedure.name.span, "procedure declared here")
+            )
+        }
+
+        let nesting_level = self.call_stack.get(enclosing).nesting_level + 1;
+        let kind = if procedure.is_function() { RecordKind::Function } else { RecordKind::Procedure };
+        let mut record = ActivationRecord::new(node.name.value.clone(), kind, nesting_level, Some(enclosing));
+
+        // assigned to like a variable, a function returns whatever was assigned last
+        if let Some(return_type) = procedure.return_type {
+            record.members.insert(node.name.value.clone(), initial_value(return_type.value));
+        }
+
+        // arguments are evaluated in the caller's record
+        let mut bound = record.value_size();
+
+        for (param, arg) in procedure.params.iter().zip(&node.args) {
+            let value = convert(param.type_spec.value, self.visit(arg)?);
+
+            bound += value.size();
+            self.check_total_value_size(bound, 0, arg.span())?;
+            record.members.insert(param.name.value.clone(), value);
+        }
+
+        self.reserve_variables(record.members.len(), node.span)?;
+        self.call_stack.push(record);
+        self.trace("ENTER");
+
+        let result = self.execute(&procedure.block);
+
+        self.trace("LEAVE");
+        let mut record = self.call_stack.pop().expect("the call's record was pushed above");
+
+        result.map(|_| record.members.remove(&node.name.value).filter(|_| procedure.is_function()))
+    }
+
+    // statements outside of a program are not type checked, so the arguments
+    // are checked here
+    fn call_builtin(&mut self, builtin: &Builtin, node: &ProcedureCall) -> Result<Value, Error> {
+        if builtin.params.len() != node.args.len() {
+            return Err(Error::runtime(
+                format!("expected {} arguments, found {}", builtin.params.len(), node.args.len()),
+                node.span
+            ))
+        }
+
+        let mut args = Vec::with_capacity(node.args.len());
+
+        for (&param, arg) in builtin.params.iter().zip(&node.args) {
+            let value = self.visit(arg)?;
+
+            if value.type_spec() != param {
+                return Err(Error::runtime(
+                    format!("expected {} argument, found {}", param, value.type_spec()),
+                    arg.span()
+                ).with_note(format!("built-in function \"{}\" takes ({})", builtin.name, signature(builtin.params))))
+            }
+
+            args.push(value);
+        }
+
+        let result = builtin.call(&args);
+
+        if let Value::String(ref string) = result {
+            self.check_value_size(string.len(), node.span)?;
+        }
+
+        Ok(result)
+    }
+
+    fn visit(&mut self, node: &Node) -> Result<Value, Error> {
+        self.step(node)?;
+
+        match node {
+            Node::Program(_) | Node::Block(_) | Node::VarDecl(_) | Node::ProcedureDecl(_) |
+            Node::If(_) | Node::While(_) | Node::Repeat(_) | Node::For(_) |
+            Node::Compound(_) | Node::NoOp(_) => Err(Error::runtime(
+                "expected expression, found statement",
+                node.span()
+            )),
+            Node::ProcedureCall(call) => self.call(call)?.ok_or_else(|| Error::runtime(
+                format!("procedure \"{}\" does not return a value", call.name.value),
+                node.span()
+            )),
+            Node::Assign(node) => {
+                let value = self.visit(&node.expr)?;
+                let index = match self.call_stack.resolve_variable(&node.name.value) {
+                    Some(index) => index,
+                    None => {
+                        self.reserve_variables(1, node.name.span)?;
+                        self.call_stack.current_index()
+                    }
+                };
+                let replaced = self.call_stack.get(index).members.get(&node.name.value).map_or(0, Value::size);
+
+                self.check_total_value_size(value.size(), replaced, node.expr.span())?;
+                self.call_stack.insert(index, node.name.value.clone(), value.clone());
+
+                Ok(value)
+            },
+            Node::Var(name) => {
+                let index = self.call_stack.resolve_variable(&name.value).ok_or_else(|| Error::runtime(
+                    format!("variable \"{}\" is not defined", name.value),
+                    name.span
+                ))?;
+
+                Ok(self.call_stack.get(index).members[&name.value].clone())
+            },
+            Node::BinaryOperator(node) => match node.token.value {
+                Token::Operator(operator @ (Operator::And | Operator::Or)) => self.logical_operation(operator, node),
+                _ => {
+                    let left = self.visit(&node.left)?;
+                    let right = self.visit(&node.right)?;
+
+                    self.binary_operation(node, left, right)
                 }
+            },
+            Node::UnaryOperator(node) if node.token.value == Token::Operator(Operator::Sub) && node.expr.is_min_integer_magnitude() => {
+                Ok(Value::Integer(i32::MIN))
+            },
+            Node::UnaryOperator(node) => {
+                let value = self.visit(&node.expr)?;
 
-                result
+                match (&node.token.value, value) {
+                    (Token::Operator(Operator::Not), Value::Boolean(value)) => Ok(Value::Boolean(!value)),
+                    (Token::Operator(Operator::Not), _) => Err(Error::runtime(
+                        "operand of Not must be a boolean",
+                        node.token.span
+                    ).with_label(node.expr.span(), "this is not a boolean")),
+                    (_, value) if !value.is_numeric() => Err(Error::runtime(
+                        format!("operand of {} must be a number", node.token.value),
+                        node.token.span
+                    ).with_label(node.expr.span(), "this is not a number")),
+                    (Token::Operator(Operator::Add), value) => Ok(value),
+                    (Token::Operator(Operator::Sub), Value::Integer(value)) => {
+                        self.integer_arithmetic(Operator::Sub, 0, value, node.token.span)
+                            .map_err(|error| Error::runtime("integer overflow in negation", error.span))
+                    },
+                    #[cfg(feature = "bignum")]
+                    (Token::Operator(Operator::Sub), Value::BigInteger(value)) => Ok(Value::big_integer(-value)),
+                    (Token::Operator(Operator::Sub), Value::Rational(value)) => {
+                        match (value.checked_neg(), self.overflow_mode) {
+                            (Some(value), _) => Ok(Value::rational(value)),
+                            (None, OverflowMode::Checked) => Err(Error::runtime("integer overflow in negation", node.token.span)),
+                            (None, _) => Ok(Value::Real(-value.to_f64()))
+                        }
+                    },
+                    (Token::Operator(Operator::Sub), Value::Real(value)) => Ok(Value::Real(-value)),
+                    _ => Err(Error::runtime(
+                        format!("expected \"+\" or \"-\", found {}", node.token.value),
+                        node.token.span
+                    ))
+                }
+            },
+            Node::Token(node) => {
+                match &node.value {
+                    Token::Number(Number::Integer(value)) => Ok(Value::Integer(*value)),
+                    #[cfg(feature = "bignum")]
+                    Token::Number(Number::BigInteger(value)) => Ok(Value::big_integer(value.clone())),
+                    Token::Number(Number::MinIntegerMagnitude) => Err(
+                        Error::runtime("integer literal out of range", node.span)
+                            .with_note(format!("the largest Integer is {}", i32::MAX))
+                    ),
+                    Token::Number(Number::Real(value)) => Ok(Value::Real(*value)),
+                    Token::TRUE => Ok(Value::Boolean(true)),
+                    Token::FALSE => Ok(Value::Boolean(false)),
+                    Token::String(value) => Ok(Value::String(value.clone())),
+                    _ => Err(Error::runtime(
+                        format!("expected Number, String, TRUE or FALSE, found {}", node.value),
+                        node.span
+                    ))
+                }
             }
         }
     }
+
+    // AND and OR only evaluate their right operand if the left one does not
+    // decide the result already
+    fn logical_operation(&mut self, operator: Operator, node: &BinaryOperator) -> Result<Value, Error> {
+        match (operator, self.boolean_operand(operator, node, &node.left)?) {
+            (Operator::And, false) => Ok(Value::Boolean(false)),
+            (Operator::Or, true) => Ok(Value::Boolean(true)),
+            _ => self.boolean_operand(operator, node, &node.right).map(Value::Boolean)
+        }
+    }
+
+    fn boolean_operand(&mut self, operator: Operator, node: &BinaryOperator, operand: &Node) -> Result<bool, Error> {
+        match self.visit(operand)? {
+            Value::Boolean(value) => Ok(value),
+            _ => Err(
+                Error::runtime(format!("operands of {} must be booleans", operator), node.token.span)
+                    .with_label(operand.span(), "this is not a boolean")
+            )
+        }
+    }
+
+    fn comparison(&self, operator: Operator, node: &BinaryOperator, left: &Value, right: &Value) -> Result<Value, Error> {
+        if !(left.is_numeric() && right.is_numeric()) && left.type_spec() != right.type_spec() {
+            return Err(
+                Error::runtime(format!("cannot compare {} with {}", left.type_spec(), right.type_spec()), node.token.span)
+                    .with_label(node.left.span(), format!("this is {}", left.type_spec()))
+                    .with_label(node.right.span(), format!("this is {}", right.type_spec()))
+            )
+        }
+
+        // NaN is unordered, so it is only ever unequal
+        let ordering = left.compare(right);
+        let result = match operator {
+            Operator::Equal => ordering == Some(Ordering::Equal),
+            Operator::NotEqual => ordering != Some(Ordering::Equal),
+            Operator::Less => ordering == Some(Ordering::Less),
+            Operator::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Operator::Greater => ordering == Some(Ordering::Greater),
+            Operator::GreaterEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            operator => unreachable!("{} is not a comparison", operator)
+        };
+
+        Ok(Value::Boolean(result))
+    }
+
+    fn binary_operation(&self, node: &BinaryOperator, left: Value, right: Value) -> Result<Value, Error> {
+        let token = &node.token;
+        let operator = match token.value {
+            Token::Operator(operator) => operator,
+            _ => return Err(Error::runtime(
+                format!("expected Operator, found {}", token.value),
+                token.span
+            ))
+        };
+
+        match (operator, left, right) {
+            (operator, ref left, ref right) if operator.is_comparison() => self.comparison(operator, node, left, right),
+            (Operator::Add, Value::String(left), Value::String(right)) => {
+                self.check_value_size(left.len() + right.len(), token.span)?;
+
+                Ok(Value::String(left + &right))
+            },
+            (Operator::Add, ref left, ref right) if matches!(left, Value::String(_)) || matches!(right, Value::String(_)) => Err(
+                Error::runtime(format!("cannot add {} to {}", right.type_spec(), left.type_spec()), token.span)
+                    .with_label(node.left.span(), format!("this is {}", left.type_spec()))
+                    .with_label(node.right.span(), format!("this is {}", right.type_spec()))
+                    .with_note("`+` only joins a STRING with another STRING")
+            ),
+            (operator, ref left, ref right) if !left.is_numeric() || !right.is_numeric() => {
+                let operand = if left.is_numeric() { &node.right } else { &node.left };
+
+                Err(
+                    Error::runtime(format!("operands of {} must be numbers", operator), token.span)
+                        .with_label(operand.span(), "this is not a number")
+                )
+            },
+            (Operator::Div, _, ref right) |
+            (Operator::IntDiv, _, ref right) |
+            (Operator::Mod, _, ref right) if right.is_zero() => Err(
+                Error::runtime("division by zero", token.span)
+                    .with_label(node.right.span(), "this evaluates to zero")
+            ),
+            // a negative exponent divides by the base
+            (Operator::Pow, ref left, ref right) if left.is_zero() && right.to_real() < 0.0 => Err(
+                Error::runtime("division by zero", token.span)
+                    .with_label(node.left.span(), "this evaluates to zero")
+            ),
+            (Operator::IntDiv, ref left, ref right) |
+            (Operator::Mod, ref left, ref right) if !left.is_integer() || !right.is_integer() => {
+                let operand = if left.is_integer() { &node.right } else { &node.left };
+
+                Err(
+                    Error::runtime(format!("operands of {} must be integers", operator), token.span)
+                        .with_label(operand.span(), "this is not an integer")
+                )
+            },
+            (Operator::Add, Value::Integer(left), Value::Integer(right)) |
+            (Operator::Sub, Value::Integer(left), Value::Integer(right)) |
+            (Operator::Mul, Value::Integer(left), Value::Integer(right)) |
+            (Operator::IntDiv, Value::Integer(left), Value::Integer(right)) |
+            (Operator::Mod, Value::Integer(left), Value::Integer(right)) => {
+                self.integer_arithmetic(operator, left, right, token.span)
+            },
+            (Operator::Pow, Value::Integer(left), Value::Integer(right)) if right >= 0 => {
+                self.integer_arithmetic(operator, left, right, token.span)
+            },
+            #[cfg(feature = "bignum")]
+            (Operator::Add, ref left, ref right) |
+            (Operator::Sub, ref left, ref right) |
+            (Operator::Mul, ref left, ref right) |
+            (Operator::IntDiv, ref left, ref right) |
+            (Operator::Mod, ref left, ref right) if left.is_integer() && right.is_integer() => {
+                self.big_integer_arithmetic(operator, left.to_big_integer().unwrap(), right.to_big_integer().unwrap(), token.span)
+            },
+            #[cfg(feature = "bignum")]
+            (Operator::Pow, ref left, Value::Integer(right)) if left.is_integer() && right >= 0 => {
+                self.big_integer_arithmetic(operator, left.to_big_integer().unwrap(), right.into(), token.span)
+            },
+            (operator, ref left, ref right) if left.is_exact() && right.is_exact() && self.is_exact_operation(operator, right) => {
+                self.rational_arithmetic(operator, left, right, token.span)
+            },
+            (Operator::IntDiv, _, _) | (Operator::Mod, _, _) => unreachable!("{} of non-integer operands", operator),
+            (operator, left, right) => Ok(real_arithmetic(operator, &left, &right))
+        }
+    }
+
+    // whether `operator` keeps exact operands exact instead of continuing with Reals
+    fn is_exact_operation(&self, operator: Operator, right: &Value) -> bool {
+        match operator {
+            Operator::Add | Operator::Sub | Operator::Mul => true,
+            Operator::Div => self.division_mode == DivisionMode::Exact,
+            // only negative exponents get here, which divide
+            Operator::Pow => self.division_mode == DivisionMode::Exact && matches!(right, Value::Integer(_)),
+            _ => false
+        }
+    }
+
+    fn rational_arithmetic(&self, operator: Operator, left: &Value, right: &Value, span: Span) -> Result<Value, Error> {
+        let result = match (left.to_rational(), right.to_rational()) {
+            (Some(left), Some(right)) => match operator {
+                Operator::Add => left.checked_add(right),
+                Operator::Sub => left.checked_sub(right),
+                Operator::Mul => left.checked_mul(right),
+                Operator::Div => left.checked_div(right),
+                Operator::Pow => i32::try_from(right.numerator()).ok().and_then(|exponent| left.checked_pow(exponent)),
+                operator => unreachable!("{} is not an exact operation", operator)
+            },
+            _ => None
+        };
+
+        // fractions neither wrap, saturate nor grow, so every mode but
+        // `Checked` gives up on exactness and continues with a Real
+        match (result, self.overflow_mode) {
+            (Some(result), _) => Ok(Value::rational(result)),
+            (None, OverflowMode::Checked) => Err(Error::runtime(format!("integer overflow in {}", operator), span)),
+            (None, _) => Ok(real_arithmetic(operator, left, right))
+        }
+    }
+
+    // `right` is never zero for `IntDiv` and `Mod` and never negative for `Pow`
+    fn integer_arithmetic(&self, operator: Operator, left: i32, right: i32, span: Span) -> Result<Value, Error> {
+        let checked = match operator {
+            Operator::Add => left.checked_add(right),
+            Operator::Sub => left.checked_sub(right),
+            Operator::Mul => left.checked_mul(right),
+            Operator::Div => None,
+            Operator::IntDiv => left.checked_div(right),
+            Operator::Mod => left.checked_rem(right),
+            Operator::Pow => left.checked_pow(right as u32),
+            operator => unreachable!("{} is not an arithmetic operator", operator)
+        };
+
+        if let Some(result) = checked {
+            return Ok(Value::Integer(result))
+        }
+
+        match (self.overflow_mode, operator) {
+            (OverflowMode::Wrapping, Operator::Add) => Ok(Value::Integer(left.wrapping_add(right))),
+            (OverflowMode::Wrapping, Operator::Sub) => Ok(Value::Integer(left.wrapping_sub(right))),
+            (OverflowMode::Wrapping, Operator::Mul) => Ok(Value::Integer(left.wrapping_mul(right))),
+            (OverflowMode::Wrapping, Operator::IntDiv) => Ok(Value::Integer(left.wrapping_div(right))),
+            (OverflowMode::Wrapping, Operator::Mod) |
+            (OverflowMode::Saturating, Operator::Mod) => Ok(Value::Integer(left.wrapping_rem(right))),
+            (OverflowMode::Wrapping, Operator::Pow) => Ok(Value::Integer(left.wrapping_pow(right as u32))),
+            (OverflowMode::Saturating, Operator::Add) => Ok(Value::Integer(left.saturating_add(right))),
+            (OverflowMode::Saturating, Operator::Sub) => Ok(Value::Integer(left.saturating_sub(right))),
+            (OverflowMode::Saturating, Operator::Mul) => Ok(Value::Integer(left.saturating_mul(right))),
+            (OverflowMode::Saturating, Operator::IntDiv) => Ok(Value::Integer(left.saturating_div(right))),
+            (OverflowMode::Saturating, Operator::Pow) => Ok(Value::Integer(left.saturating_pow(right as u32))),
+            #[cfg(feature = "bignum")]
+            (OverflowMode::Promote, operator) => self.big_integer_arithmetic(operator, left.into(), right.into(), span),
+            _ => Err(Error::runtime(format!("integer overflow in {}", operator), span))
+        }
+    }
+}
+
+// address of a local variable, how far apart two of them are tells how much
+// stack the calls in between take
+#[inline(never)]
+fn stack_position() -> usize {
+    let marker = 0u8;
+
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+// points errors raised while running a loop at the loop, innermost first
+fn in_iteration(error: Error, token: &Spanned<Token>, iteration: usize, variable: Option<(&str, i32)>) -> Error {
+    let message = match variable {
+        Some((name, value)) => format!("in iteration {} of this loop, with {} = {}", iteration, name, value),
+        None => format!("in iteration {} of this loop", iteration)
+    };
+
+    error.with_label(token.span, message)
+}
+
+// arithmetic that continues with Reals, `IntDiv` and `Mod` only
+// ever get integers
+fn real_arithmetic(operator: Operator, left: &Value, right: &Value) -> Value {
+    let (left, right) = (left.to_real(), right.to_real());
+
+    match operator {
+        Operator::Add => Value::Real(left + right),
+        Operator::Sub => Value::Real(left - right),
+        Operator::Mul => Value::Real(left * right),
+        Operator::Div => Value::Real(left / right),
+        Operator::Pow => Value::Real(left.powf(right)),
+        operator => unreachable!("{} of non-integer operands", operator)
+    }
+}
+
+// declared variables start out as zero, FALSE or the empty string
+fn initial_value(type_spec: Type) -> Value {
+    match type_spec {
+        Type::Integer => Value::Integer(0),
+        Type::Real => Value::Real(0.0),
+        Type::Boolean => Value::Boolean(false),
+        Type::String => Value::String(String::new())
+    }
+}
+
+// upper bound of the bits of the result of `big_integer_arithmetic`
+#[cfg(feature = "bignum")]
+fn big_integer_bits(operator: Operator, left: &num_bigint::BigInt, right: &num_bigint::BigInt) -> u64 {
+    use num_traits::ToPrimitive;
+
+    match operator {
+        Operator::Add | Operator::Sub => left.bits().max(right.bits()) + 1,
+        Operator::Mul => left.bits() + right.bits(),
+        // exponents too large for `u32` continue as Reals
+        Operator::Pow => match right.to_u32() {
+            Some(exponent) => {
+                let magnitude = left.to_f64().filter(|value| value.is_finite()).map_or(left.bits() as f64, |value| value.abs().log2());
+
+                (magnitude * f64::from(exponent)).ceil() as u64 + 1
+            },
+            None => 64
+        },
+        _ => left.bits()
+    }
+}
+
+#[cfg(feature = "bignum")]
+fn big_integer_arithmetic(operator: Operator, left: num_bigint::BigInt, right: num_bigint::BigInt) -> Value {
+    use num_traits::ToPrimitive;
+
+    match operator {
+        Operator::Add => Value::big_integer(left + right),
+        Operator::Sub => Value::big_integer(left - right),
+        Operator::Mul => Value::big_integer(left * right),
+        Operator::Div => Value::Real(Value::big_integer(left).to_real() / Value::big_integer(right).to_real()),
+        // both truncate towards zero, like their Integer counterparts
+        Operator::IntDiv => Value::big_integer(left / right),
+        Operator::Mod => Value::big_integer(left % right),
+        Operator::Pow => match right.to_u32() {
+            Some(exponent) => Value::big_integer(left.pow(exponent)),
+            None => Value::Real(Value::big_integer(left).to_real().powf(Value::big_integer(right).to_real()))
+        },
+        operator => unreachable!("{} is not an arithmetic operator", operator)
+    }
 }
 
-pub fn interpret(ast: &AST) -> i32 {
-    let interpreter = Interpreter {};
+pub fn interpret(ast: &AST) -> Result<Option<Value>, Error> {
+    Interpreter::default().interpret(ast)
+}
+
+#[cfg(test)]
+fn integer(value: i32) -> Node {
+    Node::Token(Spanned::new(Token::Number(Number::Integer(value)), Span::default()))
+}
+
+#[cfg(test)]
+fn operator(operator: Operator) -> Spanned<Token> {
+    Spanned::new(Token::Operator(operator), Span::default())
+}
+
+#[cfg(test)]
+fn real(value: f64) -> Node {
+    Node::Token(Spanned::new(Token::Number(Number::Real(value)), Span::default()))
+}
 
-    interpreter.visit(&ast.root)
+#[cfg(test)]
+fn binary(left: Node, operator: Operator, right: Node) -> Node {
+    Node::BinaryOperator(Box::new(BinaryOperator {
+        left,
+        token: Spanned::new(Token::Operator(operator), Span::default()),
+        right,
+        type_spec: None
+    }))
 }
 
 #[cfg(test)]
+fn unary(operator: Operator, expr: Node) -> Node {
+    Node::UnaryOperator(Box::new(UnaryOperator {
+        token: Spanned::new(Token::Operator(operator), Span::default()),
+        expr
+    }))
+}
+
 #[test]
 fn multiplication_gets_precedence_before_addition() {
     let ast = AST { 
         root: Node::BinaryOperator(Box::new(BinaryOperator {
-            left: Node::Token(Token::Number(Number::Integer(3))), 
-            token: Token::Operator(Operator::Add), 
+            left: integer(3), 
+            token: operator(Operator::Add), 
             right: Node::BinaryOperator(Box::new(BinaryOperator { 
-                left: Node::Token(Token::Number(Number::Integer(2))), 
-                token: Token::Operator(Operator::Mul), 
-                right: Node::Token(Token::Number(Number::Integer(5))) 
-            }))
+                left: integer(2), 
+                token: operator(Operator::Mul), 
+                right: integer(5),
+                type_spec: None
+            })),
+            type_spec: None
         }))
     };
     let result = interpret(&ast);
 
-    assert_eq!(result, 13);
+    assert_eq!(result, Ok(Some(Value::Integer(13))));
 }
 
 #[test]
 fn multiplication_gets_precedence_before_subtraction() {
     let ast = AST { 
         root: Node::BinaryOperator(Box::new(BinaryOperator {
-            left: Node::Token(Token::Number(Number::Integer(3))), 
-            token: Token::Operator(Operator::Sub), 
+            left: integer(3), 
+            token: operator(Operator::Sub), 
             right: Node::BinaryOperator(Box::new(BinaryOperator { 
-                left: Node::Token(Token::Number(Number::Integer(2))), 
-                token: Token::Operator(Operator::Mul), 
-                right: Node::Token(Token::Number(Number::Integer(5))) 
-            }))
+                left: integer(2), 
+                token: operator(Operator::Mul), 
+                right: integer(5),
+                type_spec: None
+            })),
+            type_spec: None
         }))
     };
     let result = interpret(&ast);
-    assert_eq!(result, -7);
+    assert_eq!(result, Ok(Some(Value::Integer(-7))));
 }
 
 #[test]
 fn division_gets_precedence_before_addition() {
     let ast = AST { 
         root: Node::BinaryOperator(Box::new(BinaryOperator {
-            left: Node::Token(Token::Number(Number::Integer(3))), 
-            token: Token::Operator(Operator::Add), 
+            left: integer(3), 
+            token: operator(Operator::Add), 
             right: Node::BinaryOperator(Box::new(BinaryOperator { 
-                left: Node::Token(Token::Number(Number::Integer(10))), 
-                token: Token::Operator(Operator::Div), 
-                right: Node::Token(Token::Number(Number::Integer(5))) 
-            }))
+                left: integer(10), 
+                token: operator(Operator::Div), 
+                right: integer(5),
+                type_spec: None
+            })),
+            type_spec: None
         }))
     };
     let result = interpret(&ast);
-    assert_eq!(result, 5);
+    assert_eq!(result, Ok(Some(Value::Real(5.0))));
 }
 
 #[test]
 fn division_gets_precedence_before_subtraction() {
     let ast = AST { 
         root: Node::BinaryOperator(Box::new(BinaryOperator {
-            left: Node::Token(Token::Number(Number::Integer(3))), 
-            token: Token::Operator(Operator::Sub), 
+            left: integer(3), 
+            token: operator(Operator::Sub), 
             right: Node::BinaryOperator(Box::new(BinaryOperator { 
-                left: Node::Token(Token::Number(Number::Integer(6))), 
-                token: Token::Operator(Operator::Div), 
-                right: Node::Token(Token::Number(Number::Integer(2))) 
-            }))
+                left: integer(6), 
+                token: operator(Operator::Div), 
+                right: integer(2),
+                type_spec: None
+            })),
+            type_spec: None
         }))
     };
     let result = interpret(&ast);
-    assert_eq!(result, 0);
+    assert_eq!(result, Ok(Some(Value::Real(0.0))));
 }
 
 #[test]
 fn parenthesized_expressions_get_precedence_on_left_hand_of_operator() {
     let ast = AST { 
         root: Node::BinaryOperator(Box::new(BinaryOperator {
-            left: Node::Token(Token::Number(Number::Integer(3))), 
-            token: Token::Operator(Operator::Mul), 
+            left: integer(3), 
+            token: operator(Operator::Mul), 
             right: Node::BinaryOperator(Box::new(BinaryOperator { 
-                left: Node::Token(Token::Number(Number::Integer(200))), 
-                token: Token::Operator(Operator::Add), 
-                right: Node::Token(Token::Number(Number::Integer(50))) 
-            }))
+                left: integer(200), 
+                token: operator(Operator::Add), 
+                right: integer(50),
+                type_spec: None
+            })),
+            type_spec: None
         }))
     };
     let result = interpret(&ast);
-    assert_eq!(result, 750);
+    assert_eq!(result, Ok(Some(Value::Integer(750))));
 }
 
 #[test]
 fn parenthesized_expressions_get_precedence_on_right_hand_of_operator() {
     let ast = AST { 
         root: Node::BinaryOperator(Box::new(BinaryOperator {
-            left: Node::Token(Token::Number(Number::Integer(3))), 
-            token: Token::Operator(Operator::Mul), 
+            left: integer(3), 
+            token: operator(Operator::Mul), 
             right: Node::BinaryOperator(Box::new(BinaryOperator { 
-                left: Node::Token(Token::Number(Number::Integer(100))), 
-                token: Token::Operator(Operator::Add), 
-                right: Node::Token(Token::Number(Number::Integer(50))) 
-            }))
+                left: integer(100), 
+                token: operator(Operator::Add), 
+                right: integer(50),
+                type_spec: None
+            })),
+            type_spec: None
         }))
     };
     let result = interpret(&ast);
-    assert_eq!(result, 450);
-                                                                                                                                                                                                                                                                                                }
\ No newline at end of file
+    assert_eq!(result, Ok(Some(Value::Integer(450))));
+}
+
+#[test]
+fn non_operator_token_in_binary_operator_is_a_runtime_error() {
+    let ast = AST {
+        root: Node::BinaryOperator(Box::new(BinaryOperator {
+            left: integer(1),
+            token: Spanned::new(Token::LPAREN, Span::default()),
+            right: integer(2),
+            type_spec: None
+        }))
+    };
+    let error = interpret(&ast).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Runtime);
+}
+
+#[test]
+fn division_of_integers_returns_a_real() {
+    let ast = AST { root: binary(integer(7), Operator::Div, integer(2)) };
+
+    assert_eq!(interpret(&ast), Ok(Some(Value::Real(3.5))));
+}
+
+#[test]
+fn integer_operands_are_promoted_when_mixed_with_reals() {
+    let ast = AST { root: binary(integer(2), Operator::Mul, real(1.5)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Real(3.0))));
+
+    let ast = AST { root: binary(real(0.5), Operator::Add, integer(1)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Real(1.5))));
+}
+
+#[test]
+fn integer_arithmetic_stays_integer() {
+    let ast = AST { root: binary(integer(7), Operator::Sub, integer(2)) };
+
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(5))));
+}
+
+#[test]
+fn unary_minus_negates_its_operand() {
+    let ast = AST { root: unary(Operator::Sub, integer(3)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(-3))));
+
+    let ast = AST { root: unary(Operator::Sub, unary(Operator::Sub, real(1.5))) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Real(1.5))));
+}
+
+#[test]
+fn unary_plus_returns_its_operand() {
+    let ast = AST { root: binary(integer(2), Operator::Mul, unary(Operator::Add, integer(4))) };
+
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(8))));
+}
+
+#[test]
+fn integer_overflow_is_a_runtime_error_at_the_operator() {
+    let ast = AST {
+        root: Node::BinaryOperator(Box::new(BinaryOperator {
+            left: integer(i32::MAX),
+            token: Spanned::new(Token::Operator(Operator::Add), Span::new(11, 12, 1, 12)),
+            right: integer(1),
+            type_spec: None
+        }))
+    };
+    let error = Interpreter::new(OverflowMode::Checked).interpret(&ast).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Runtime);
+    assert_eq!(error.to_string(), "Runtime Error: integer overflow in Add at 1:12");
+}
+
+#[test]
+fn negating_the_smallest_integer_overflows() {
+    let ast = AST { root: unary(Operator::Sub, binary(unary(Operator::Sub, integer(i32::MAX)), Operator::Sub, integer(1))) };
+
+    let error = Interpreter::new(OverflowMode::Checked).interpret(&ast).unwrap_err();
+
+    assert_eq!(error.message, "integer overflow in negation");
+}
+
+#[test]
+fn the_smallest_integer_can_be_written_as_a_literal() {
+    let result = |text: &str| {
+        Interpreter::new(OverflowMode::Checked).interpret(&crate::parser::parse(crate::lexer::lex(text).unwrap()).unwrap())
+    };
+
+    assert_eq!(result("-2147483648"), Ok(Some(Value::Integer(i32::MIN))));
+    assert_eq!(result("1 + (-2147483648)"), Ok(Some(Value::Integer(i32::MIN + 1))));
+    assert_eq!(result("--2147483648").unwrap_err().message, "integer overflow in negation");
+    assert_eq!(result("-(2147483648)"), Ok(Some(Value::Integer(i32::MIN))));
+}
+
+#[cfg(not(feature = "bignum"))]
+#[test]
+fn the_digits_of_the_smallest_integer_are_out_of_range_unless_negated() {
+    let result = |text: &str| run_program(&mut Interpreter::default(), text);
+
+    let error = result("1 - 2147483648").unwrap_err();
+    assert_eq!(error.message, "integer literal out of range");
+    assert_eq!(error.span, Span::new(4, 14, 1, 5));
+    assert_eq!(result("+2147483648").unwrap_err().message, "integer literal out of range");
+}
+
+#[test]
+fn negating_the_smallest_integer_binds_like_any_other_negation() {
+    let result = |text: &str| run_program(&mut Interpreter::new(OverflowMode::Checked), text);
+
+    // both negate the power, `-2147483648 ** 2` is not `(-2147483648) ** 2`
+    assert_eq!(result("-2 ** 2"), Ok(Some(Value::Integer(-4))));
+    #[cfg(not(feature = "bignum"))]
+    assert_eq!(result("-2147483648 ** 2").unwrap_err().message, "integer literal out of range");
+    #[cfg(feature = "bignum")]
+    assert_eq!(result("-2147483648 ** 2"), Ok(Some(Value::big_integer(-num_bigint::BigInt::from(2).pow(62)))));
+    assert_eq!(result("(-2147483648) ** 2").unwrap_err().message, "integer overflow in Pow");
+}
+
+#[test]
+fn division_by_zero_is_a_runtime_error() {
+    let ast = AST { root: binary(integer(1), Operator::Div, integer(0)) };
+    assert_eq!(interpret(&ast).unwrap_err().message, "division by zero");
+
+    let ast = AST { root: binary(real(1.5), Operator::Div, real(0.0)) };
+    assert_eq!(interpret(&ast).unwrap_err().message, "division by zero");
+}
+
+#[test]
+fn wrapping_mode_wraps_around_on_overflow() {
+    let mut interpreter = Interpreter::new(OverflowMode::Wrapping);
+    let ast = AST { root: binary(integer(i32::MAX), Operator::Add, integer(1)) };
+
+    assert_eq!(interpreter.interpret(&ast), Ok(Some(Value::Integer(i32::MIN))));
+}
+
+#[test]
+fn saturating_mode_clamps_on_overflow() {
+    let mut interpreter = Interpreter::new(OverflowMode::Saturating);
+    let ast = AST { root: binary(integer(i32::MAX), Operator::Mul, integer(2)) };
+    assert_eq!(interpreter.interpret(&ast), Ok(Some(Value::Integer(i32::MAX))));
+
+    let ast = AST { root: binary(unary(Operator::Sub, integer(i32::MAX)), Operator::Sub, integer(5)) };
+    assert_eq!(interpreter.interpret(&ast), Ok(Some(Value::Integer(i32::MIN))));
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn promote_mode_continues_with_big_integers() {
+    use num_bigint::BigInt;
+
+    let ast = AST { root: binary(integer(i32::MAX), Operator::Mul, integer(i32::MAX)) };
+    let result = Interpreter::new(OverflowMode::Promote).interpret(&ast);
+    assert_eq!(result, Ok(Some(Value::BigInteger(BigInt::from(i32::MAX) * BigInt::from(i32::MAX)))));
+
+    let ast = AST { root: binary(binary(integer(i32::MAX), Operator::Add, integer(1)), Operator::Sub, integer(1)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(i32::MAX))));
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn big_integers_support_every_integer_operation() {
+    use num_bigint::BigInt;
+
+    let interpreter = &mut Interpreter::new(OverflowMode::Promote);
+    let big = |text: &str| Value::BigInteger(text.parse::<BigInt>().unwrap());
+
+    assert_eq!(run_program(interpreter, "2 ** 70 DIV 3"), Ok(Some(big("393530540239137101141"))));
+    assert_eq!(run_program(interpreter, "-(2 ** 70) DIV 3"), Ok(Some(big("-393530540239137101141"))));
+    assert_eq!(run_program(interpreter, "2 ** 70 % 1000"), Ok(Some(Value::Integer(424))));
+    assert_eq!(run_program(interpreter, "-(2 ** 70) % 1000"), Ok(Some(Value::Integer(-424))));
+    assert_eq!(run_program(interpreter, "(2 ** 40) ** 2"), Ok(Some(big("1208925819614629174706176"))));
+    assert_eq!(run_program(interpreter, "2 ** 70 DIV 0").unwrap_err().message, "division by zero");
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn big_integers_compare_with_every_number() {
+    let interpreter = &mut Interpreter::new(OverflowMode::Promote);
+
+    assert_eq!(run_program(interpreter, "2 ** 70 > 2 ** 69"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(run_program(interpreter, "2 ** 70 = 2 ** 70"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(run_program(interpreter, "-(2 ** 70) < -2147483648"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(run_program(interpreter, "2 ** 70 <> 2147483647"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(run_program(interpreter, "2 ** 70 >= 1.5e21"), Ok(Some(Value::Boolean(false))));
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn big_integers_mixed_with_reals_become_real() {
+    let interpreter = &mut Interpreter::new(OverflowMode::Promote);
+
+    assert_eq!(run_program(interpreter, "2 ** 70 * 0.5"), Ok(Some(Value::Real(2f64.powi(69)))));
+    assert_eq!(run_program(interpreter, "2 ** 70 + 1.0"), Ok(Some(Value::Real(2f64.powi(70)))));
+    assert_eq!(run_program(interpreter, "2 ** 70 / 2 ** 68"), Ok(Some(Value::Real(4.0))));
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn big_integer_results_that_fit_become_integers_again() {
+    let interpreter = &mut Interpreter::new(OverflowMode::Promote);
+
+    assert_eq!(run_program(interpreter, "2 ** 70 - 2 ** 70 + 1"), Ok(Some(Value::Integer(1))));
+    assert_eq!(run_program(interpreter, "2 ** 70 DIV 2 ** 69"), Ok(Some(Value::Integer(2))));
+    assert_eq!(run_program(interpreter, "2 ** 70 % 2 ** 69"), Ok(Some(Value::Integer(0))));
+    assert_eq!(run_program(interpreter, "(2147483647 + 1) * -1"), Ok(Some(Value::Integer(i32::MIN))));
+}
+
+#[test]
+fn exact_division_mode_keeps_fractions_exact() {
+    let mut interpreter = Interpreter { division_mode: DivisionMode::Exact, ..Interpreter::default() };
+    let ast = AST {
+        root: binary(
+            binary(integer(1), Operator::Div, integer(3)),
+            Operator::Add,
+            binary(integer(1), Operator::Div, integer(6))
+        )
+    };
+
+    assert_eq!(interpreter.interpret(&ast), Ok(Some(Value::Rational(Rational::new(1, 2).unwrap()))));
+}
+
+#[test]
+fn exact_division_mode_returns_integers_for_whole_results() {
+    let mut interpreter = Interpreter { division_mode: DivisionMode::Exact, ..Interpreter::default() };
+    let ast = AST { root: binary(binary(integer(1), Operator::Div, integer(3)), Operator::Mul, integer(6)) };
+
+    assert_eq!(interpreter.interpret(&ast), Ok(Some(Value::Integer(2))));
+}
+
+#[test]
+fn rationals_mixed_with_reals_become_real() {
+    let mut interpreter = Interpreter { division_mode: DivisionMode::Exact, ..Interpreter::default() };
+    let ast = AST { root: unary(Operator::Sub, binary(binary(integer(1), Operator::Div, integer(4)), Operator::Add, real(0.5))) };
+
+    assert_eq!(interpreter.interpret(&ast), Ok(Some(Value::Real(-0.75))));
+}
+
+#[test]
+fn rational_overflow_continues_with_reals_unless_checked() {
+    let part = || binary(integer(1), Operator::Div, integer(2147483647));
+    let ast = AST { root: binary(binary(part(), Operator::Mul, part()), Operator::Mul, part()) };
+
+    let mut interpreter = Interpreter { division_mode: DivisionMode::Exact, ..Interpreter::new(OverflowMode::Checked) };
+    assert_eq!(interpreter.interpret(&ast).unwrap_err().message, "integer overflow in Mul");
+
+    let mut interpreter = Interpreter { division_mode: DivisionMode::Exact, ..Interpreter::new(OverflowMode::Saturating) };
+    assert_eq!(interpreter.interpret(&ast), Ok(Some(Value::Real(2147483647f64.powi(-3)))));
+}
+
+#[test]
+fn integer_division_and_modulo_truncate_towards_zero() {
+    let ast = AST { root: binary(integer(7), Operator::IntDiv, integer(2)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(3))));
+
+    let ast = AST { root: binary(unary(Operator::Sub, integer(7)), Operator::IntDiv, integer(2)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(-3))));
+
+    let ast = AST { root: binary(unary(Operator::Sub, integer(7)), Operator::Mod, integer(2)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(-1))));
+}
+
+#[test]
+fn integer_division_and_modulo_by_zero_are_runtime_errors() {
+    let ast = AST { root: binary(integer(7), Operator::IntDiv, integer(0)) };
+    assert_eq!(interpret(&ast).unwrap_err().message, "division by zero");
+
+    let ast = AST { root: binary(integer(7), Operator::Mod, integer(0)) };
+    assert_eq!(interpret(&ast).unwrap_err().message, "division by zero");
+}
+
+#[test]
+fn integer_division_of_reals_is_a_runtime_error() {
+    let ast = AST { root: binary(real(7.5), Operator::Mod, integer(2)) };
+    let error = interpret(&ast).unwrap_err();
+
+    assert_eq!(error.message, "operands of Mod must be integers");
+    assert_eq!(error.labels[0].message, "this is not an integer");
+}
+
+#[test]
+fn integer_division_of_the_smallest_integer_by_minus_one_overflows() {
+    let smallest = binary(unary(Operator::Sub, integer(i32::MAX)), Operator::Sub, integer(1));
+    let ast = AST { root: binary(smallest, Operator::IntDiv, unary(Operator::Sub, integer(1))) };
+
+    let error = Interpreter::new(OverflowMode::Checked).interpret(&ast).unwrap_err();
+    assert_eq!(error.message, "integer overflow in IntDiv");
+    assert_eq!(Interpreter::new(OverflowMode::Saturating).interpret(&ast), Ok(Some(Value::Integer(i32::MAX))));
+}
+
+#[test]
+fn exponentiation_of_integers_stays_integer() {
+    let ast = AST { root: binary(integer(2), Operator::Pow, integer(10)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(1024))));
+
+    let ast = AST { root: binary(unary(Operator::Sub, integer(2)), Operator::Pow, integer(3)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(-8))));
+
+    let ast = AST { root: binary(integer(0), Operator::Pow, integer(0)) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Integer(1))));
+
+    let ast = AST { root: binary(integer(2), Operator::Pow, integer(31)) };
+    assert_eq!(Interpreter::new(OverflowMode::Checked).interpret(&ast).unwrap_err().message, "integer overflow in Pow");
+    assert_eq!(Interpreter::new(OverflowMode::Saturating).interpret(&ast), Ok(Some(Value::Integer(i32::MAX))));
+}
+
+#[test]
+fn negative_exponents_divide() {
+    let ast = AST { root: binary(integer(2), Operator::Pow, unary(Operator::Sub, integer(2))) };
+    assert_eq!(interpret(&ast), Ok(Some(Value::Real(0.25))));
+
+    let mut interpreter = Interpreter { division_mode: DivisionMode::Exact, ..Interpreter::default() };
+    assert_eq!(interpreter.interpret(&ast), Ok(Some(Value::Rational(Rational::new(1, 4).unwrap()))));
+
+    let ast = AST { root: binary(integer(0), Operator::Pow, unary(Operator::Sub, integer(1))) };
+    assert_eq!(interpret(&ast).unwrap_err().message, "division by zero");
+}
+
+#[test]
+fn exponentiation_of_reals_returns_a_real() {
+    let ast = AST { root: binary(real(4.0), Operator::Pow, real(0.5)) };
+
+    assert_eq!(interpret(&ast), Ok(Some(Value::Real(2.0))));
+}
+
+#[cfg(test)]
+fn var(name: &str) -> Node {
+    Node::Var(Spanned::new(name.to_string(), Span::default()))
+}
+
+#[cfg(test)]
+fn assign(name: &str, expr: Node) -> Node {
+    Node::Assign(Box::new(Assign {
+        name: Spanned::new(name.to_string(), Span::default()),
+        token: Spanned::new(Token::ASSIGN, Span::default()),
+        expr
+    }))
+}
+
+#[test]
+fn assignments_are_remembered_across_statements() {
+    let interpreter = &mut Interpreter::default();
+
+    assert_eq!(interpreter.interpret(&AST { root: assign("x", binary(integer(3), Operator::Mul, integer(4))) }), Ok(Some(Value::Integer(12))));
+    assert_eq!(interpreter.interpret(&AST { root: assign("y", binary(var("x"), Operator::Add, integer(1))) }), Ok(Some(Value::Integer(13))));
+    assert_eq!(interpreter.interpret(&AST { root: binary(var("y"), Operator::Sub, var("x")) }), Ok(Some(Value::Integer(1))));
+    assert_eq!(interpreter.call_stack.global().members.get("x"), Some(&Value::Integer(12)));
+}
+
+#[test]
+fn undefined_variables_are_a_runtime_error() {
+    let error = interpret(&AST { root: binary(var("x"), Operator::Add, integer(1)) }).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Runtime);
+    assert_eq!(error.message, "variable \"x\" is not defined");
+}
+
+#[test]
+fn programs_execute_their_statements_in_order() {
+    let interpreter = &mut Interpreter::default();
+    let tokens = crate::lexer::lex("PROGRAM p; VAR x, y : INTEGER; BEGIN x := 2; BEGIN y := x * 3; x := y - 1 END; END.").unwrap();
+
+    assert_eq!(interpreter.interpret(&crate::parser::parse(tokens).unwrap()), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("x"), Some(&Value::Integer(5)));
+    assert_eq!(interpreter.call_stack.global().members.get("y"), Some(&Value::Integer(6)));
+}
+
+#[cfg(test)]
+fn run_program(interpreter: &mut Interpreter, source: &str) -> Result<Option<Value>, Error> {
+    interpreter.interpret(&crate::parser::parse(crate::lexer::lex(source).unwrap()).unwrap())
+}
+
+#[test]
+fn declared_variables_are_initialized_to_zero() {
+    let interpreter = &mut Interpreter::default();
+
+    assert_eq!(run_program(interpreter, "PROGRAM p; VAR a : INTEGER; y : REAL; BEGIN END."), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("a"), Some(&Value::Integer(0)));
+    assert_eq!(interpreter.call_stack.global().members.get("y"), Some(&Value::Real(0.0)));
+}
+
+#[test]
+fn assignment_to_undeclared_variables_in_programs_is_a_runtime_error() {
+    let error = run_program(&mut Interpreter::default(), "PROGRAM p; VAR a : INTEGER; BEGIN a := 1; b := a END.").unwrap_err();
+
+    assert_eq!(error.message, "variable \"b\" is not declared");
+    assert_eq!(error.span, Span::new(42, 43, 1, 43));
+}
+
+#[test]
+fn integers_assigned_to_real_variables_become_real() {
+    let interpreter = &mut Interpreter::default();
+
+    assert_eq!(run_program(interpreter, "PROGRAM p; VAR y : REAL; BEGIN y := 2 * 3 END."), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("y"), Some(&Value::Real(6.0)));
+}
+
+#[test]
+fn booleans_in_arithmetic_are_a_runtime_error() {
+    let interpreter = &mut Interpreter::default();
+    interpreter.call_stack.insert(0, "b", Value::Boolean(true));
+
+    let error = interpreter.interpret(&AST { root: binary(integer(1), Operator::Add, var("b")) }).unwrap_err();
+
+    assert_eq!(error.message, "operands of Add must be numbers");
+}
+
+#[test]
+fn procedures_see_their_parameters_and_the_enclosing_scope() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR x : INTEGER; y : REAL; \
+        PROCEDURE set(a : INTEGER; b : REAL); VAR c : INTEGER; BEGIN c := a * 2; x := c + 1; y := b END; \
+        BEGIN set(3, 4) END.";
+
+    assert_eq!(run_program(interpreter, source), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("x"), Some(&Value::Integer(7)));
+    assert_eq!(interpreter.call_stack.global().members.get("y"), Some(&Value::Real(4.0)));
+    assert_eq!(interpreter.call_stack.global().members.get("c"), None);
+    assert_eq!(interpreter.call_stack.len(), 1);
+}
+
+#[test]
+fn every_call_gets_a_new_activation_record() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR total : INTEGER; \
+        PROCEDURE add(n : INTEGER); VAR local : INTEGER; BEGIN local := local + n; total := total + local END; \
+        BEGIN total := 0; add(2); add(3) END.";
+
+    assert_eq!(run_program(interpreter, source), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("total"), Some(&Value::Integer(5)));
+}
+
+#[test]
+fn nested_procedures_resolve_variables_lexically() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR x, result : INTEGER; \
+        PROCEDURE outer(x : INTEGER); \
+            PROCEDURE inner; BEGIN result := x END; \
+        BEGIN inner END; \
+        PROCEDURE caller; VAR x : INTEGER; BEGIN x := 99; outer(5) END; \
+        BEGIN x := 1; caller END.";
+
+    assert_eq!(run_program(interpreter, source), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("result"), Some(&Value::Integer(5)));
+}
+
+#[test]
+fn functions_return_the_value_assigned_to_their_name() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR x : INTEGER; y : REAL; \
+        FUNCTION square(n : INTEGER) : INTEGER; BEGIN square := n * n END; \
+        FUNCTION half(n : INTEGER) : REAL; BEGIN half := n END; \
+        BEGIN x := square(3) + square(square(2)); y := half(3) END.";
+
+    assert_eq!(run_program(interpreter, source), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("x"), Some(&Value::Integer(25)));
+    assert_eq!(interpreter.call_stack.global().members.get("y"), Some(&Value::Real(3.0)));
+}
+
+#[test]
+fn calls_nested_deeper_than_the_maximum_are_runtime_errors() {
+    let interpreter = &mut Interpreter { limits: Limits { max_call_depth: 50, ..Limits::default() }, ..Interpreter::default() };
+    let source = "PROGRAM p; VAR x : INTEGER; \
+        FUNCTION forever(n : INTEGER) : INTEGER; BEGIN forever := forever(n + 1) END; \
+        BEGIN x := forever(0) END.";
+    let error = run_program(interpreter, source).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Runtime);
+    assert_eq!(error.message, "maximum call depth of 50 exceeded");
+    assert_eq!(interpreter.call_stack.len(), 1);
+}
+
+#[test]
+fn the_default_call_depth_fits_into_the_stack_size() {
+    let source = "PROGRAM p; VAR x : INTEGER; \
+        FUNCTION forever(n : INTEGER) : INTEGER; BEGIN forever := 1 + forever(n + 1) * 2 END; \
+        BEGIN x := forever(0) END.";
+
+    // on the test's own thread, which has a far smaller stack than `STACK_SIZE`
+    assert_eq!(run_program(&mut Interpreter::default(), source).unwrap_err().message, "maximum call depth of 1000 exceeded");
+    assert_eq!(
+        interpret(&crate::parser::parse(crate::lexer::lex(source).unwrap()).unwrap()).unwrap_err().message,
+        "maximum call depth of 1000 exceeded"
+    );
+}
+
+#[test]
+fn call_depths_beyond_the_stack_size_are_runtime_errors() {
+    let source = "PROGRAM p; VAR x : INTEGER; \
+        FUNCTION forever(n : INTEGER) : INTEGER; BEGIN forever := 1 + forever(n + 1) * 2 END; \
+        BEGIN x := forever(0) END.";
+    let limits = Limits { max_call_depth: 100_000_000, ..Limits::default() };
+    let error = run_program(&mut Interpreter { limits, ..Interpreter::default() }, source).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Runtime);
+    assert!(error.message.starts_with("maximum call depth exceeded after "), "{}", error.message);
+}
+
+#[test]
+fn programs_nested_up_to_the_default_depth_fit_into_an_ordinary_thread() {
+    let program = |body: String| format!(
+        "PROGRAM p; VAR x : INTEGER; FUNCTION f(n : INTEGER) : INTEGER; BEGIN f := n END; BEGIN {} END.",
+        body
+    );
+    let nestings: &[&dyn Fn(usize) -> String] = &[
+        &|n| program(format!("x := {}1{}", "(".repeat(n), ")".repeat(n))),
+        &|n| program(format!("x := {}1", "- ".repeat(n))),
+        &|n| program(format!("x := 1{}", " + 1".repeat(n))),
+        &|n| program(format!("x := {}1{}", "f(".repeat(n), ")".repeat(n))),
+        &|n| program(format!("{}x := 1", "IF TRUE THEN ".repeat(n))),
+        &|n| program(format!("{}x := 1{}", "BEGIN ".repeat(n), " END".repeat(n))),
+        &|n| format!("PROGRAM p; VAR x : INTEGER; {}BEGIN x := 1 END.", "PROCEDURE q; ".repeat(n) + &"BEGIN END; ".repeat(n))
+    ];
+
+    // on the test's own thread, parsing and analysis do not get a bigger stack
+    for nesting in nestings {
+        let parse = |n: usize| crate::parser::parse(crate::lexer::lex(&nesting(n)).unwrap());
+        let deepest = (1..).find(|&n| parse(n).is_err()).unwrap() - 1;
+        let mut ast = parse(deepest).unwrap();
+
+        assert!(crate::semantic::analyze(&ast).is_ok(), "{}", nesting(deepest));
+        assert!(crate::semantic::check(&mut ast).is_ok(), "{}", nesting(deepest));
+        assert!(Interpreter::default().interpret(&ast).is_ok(), "{}", nesting(deepest));
+    }
+}
+
+#[test]
+fn comparisons_compare_numbers_by_value() {
+    let mut interpreter = Interpreter::default();
+    let result = |interpreter: &mut Interpreter, text: &str| {
+        interpreter.interpret(&crate::parser::parse(crate::lexer::lex(text).unwrap()).unwrap())
+    };
+
+    assert_eq!(result(&mut interpreter, "1 = 1.0"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(result(&mut interpreter, "2 <= 1"), Ok(Some(Value::Boolean(false))));
+    assert_eq!(result(&mut interpreter, "1 / 3 <> 0.5"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(result(&mut interpreter, "FALSE < TRUE"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(result(&mut interpreter, "TRUE >= 1").unwrap_err().message, "cannot compare BOOLEAN with INTEGER");
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let mut interpreter = Interpreter::default();
+    let result = |interpreter: &mut Interpreter, text: &str| {
+        interpreter.interpret(&crate::parser::parse(crate::lexer::lex(text).unwrap()).unwrap())
+    };
+
+    assert_eq!(result(&mut interpreter, "FALSE AND (1 DIV 0 = 0)"), Ok(Some(Value::Boolean(false))));
+    assert_eq!(result(&mut interpreter, "TRUE OR (1 DIV 0 = 0)"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(result(&mut interpreter, "TRUE AND NOT (1 > 2)"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(result(&mut interpreter, "TRUE AND (1 DIV 0 = 0)").unwrap_err().message, "division by zero");
+    assert_eq!(result(&mut interpreter, "FALSE OR 1").unwrap_err().message, "operands of Or must be booleans");
+}
+
+#[test]
+fn if_statements_execute_one_branch() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR x, y : INTEGER; \
+        FUNCTION factorial(n : INTEGER) : INTEGER; \
+        BEGIN IF n <= 1 THEN factorial := 1 ELSE factorial := n * factorial(n - 1) END; \
+        BEGIN \
+            x := factorial(5); \
+            IF x > 100 THEN IF x > 1000 THEN y := 1 ELSE y := 2; \
+            IF FALSE THEN x := 0 \
+        END.";
+
+    assert_eq!(run_program(interpreter, source), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("x"), Some(&Value::Integer(120)));
+    assert_eq!(interpreter.call_stack.global().members.get("y"), Some(&Value::Integer(2)));
+}
+
+#[test]
+fn loops_run_until_their_condition_decides() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR i, sum, n, steps : INTEGER; \
+        BEGIN \
+            FOR i := 1 TO 10 DO sum := sum + i; \
+            FOR i := 3 DOWNTO 1 DO n := n * 10 + i; \
+            FOR i := 1 TO 0 DO sum := 0; \
+            WHILE sum > 50 DO sum := sum - 7; \
+            REPEAT steps := steps + 1 UNTIL TRUE \
+        END.";
+
+    assert_eq!(run_program(interpreter, source), Ok(None));
+
+    let members = &interpreter.call_stack.global().members;
+    assert_eq!(members.get("sum"), Some(&Value::Integer(48)));
+    assert_eq!(members.get("n"), Some(&Value::Integer(321)));
+    assert_eq!(members.get("i"), Some(&Value::Integer(1)));
+    assert_eq!(members.get("steps"), Some(&Value::Integer(1)));
+}
+
+#[test]
+fn for_loops_count_a_declared_variable_that_keeps_its_last_value() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR i, j, k : INTEGER; \
+        BEGIN FOR i := 1 TO 5 DO; j := 7; FOR j := 1 TO 0 DO; FOR k := 2 DOWNTO -2 DO END.";
+
+    assert_eq!(run_program(interpreter, source), Ok(None));
+
+    let members = &interpreter.call_stack.global().members;
+    assert_eq!(members.get("i"), Some(&Value::Integer(5)));
+    assert_eq!(members.get("j"), Some(&Value::Integer(7)));
+    assert_eq!(members.get("k"), Some(&Value::Integer(-2)));
+}
+
+#[test]
+fn errors_in_loops_report_the_iteration() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR i, x : INTEGER; BEGIN FOR i := 3 DOWNTO -3 DO x := 6 DIV i END.";
+    let error = run_program(interpreter, source).unwrap_err();
+
+    assert_eq!(error.message, "division by zero");
+    assert_eq!(error.labels[1].message, "in iteration 4 of this loop, with i = 0");
+}
+
+#[test]
+fn procedures_cannot_assign_to_a_running_loop_variable() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR i : INTEGER; PROCEDURE skip; BEGIN i := 10 END; BEGIN FOR i := 1 TO 3 DO skip END.";
+    let error = run_program(interpreter, source).unwrap_err();
+
+    assert_eq!(error.message, "cannot assign to loop variable \"i\"");
+    assert_eq!(error.labels[0].message, "in iteration 1 of this loop, with i = 1");
+}
+
+#[test]
+fn strings_concatenate_compare_and_call_builtins() {
+    let mut interpreter = Interpreter::default();
+    let result = |interpreter: &mut Interpreter, text: &str| {
+        interpreter.interpret(&crate::parser::parse(crate::lexer::lex(text).unwrap()).unwrap())
+    };
+    let string = |value: &str| Ok(Some(Value::String(value.to_string())));
+
+    assert_eq!(result(&mut interpreter, "s := 'it''s' + ' ' + upcase('ok')"), string("it's OK"));
+    assert_eq!(result(&mut interpreter, "copy(s, pos('OK', s), length(s))"), string("OK"));
+    assert_eq!(result(&mut interpreter, "'abc' < 'abd'"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(result(&mut interpreter, "s = 'it''s OK'"), Ok(Some(Value::Boolean(true))));
+    assert_eq!(result(&mut interpreter, "'1' = 1").unwrap_err().message, "cannot compare STRING with INTEGER");
+    assert_eq!(result(&mut interpreter, "s + 1").unwrap_err().message, "cannot add INTEGER to STRING");
+    assert_eq!(result(&mut interpreter, "length(1)").unwrap_err().message, "expected STRING argument, found INTEGER");
+    assert_eq!(result(&mut interpreter, "upcase()").unwrap_err().message, "expected 1 arguments, found 0");
+    assert_eq!(result(&mut interpreter, "LENGTH('x') + Length(UPCASE('größe'))"), Ok(Some(Value::Integer(6))));
+}
+
+#[test]
+fn declared_procedures_shadow_builtins_and_strings_start_empty() {
+    let interpreter = &mut Interpreter::default();
+    let source = "PROGRAM p; VAR s : STRING; n : INTEGER; \
+        FUNCTION length(a : INTEGER) : INTEGER; BEGIN length := a * 2 END; \
+        BEGIN n := length(3); IF s = '' THEN s := 'empty' END.";
+
+    assert_eq!(run_program(interpreter, source), Ok(None));
+    assert_eq!(interpreter.call_stack.global().members.get("n"), Some(&Value::Integer(6)));
+    assert_eq!(interpreter.call_stack.global().members.get("s"), Some(&Value::String("empty".to_string())));
+}
+
+#[test]
+fn strings_longer_than_the_value_size_are_runtime_errors() {
+    let limits = Limits { max_value_size: Some(8), ..Limits::default() };
+    let interpreter = &mut Interpreter { limits, ..Interpreter::default() };
+    let source = "PROGRAM p; VAR s : STRING; BEGIN s := 'ab'; WHILE TRUE DO s := s + s END.";
+    let error = run_program(interpreter, source).unwrap_err();
+
+    assert_eq!(error.message, "value of 16 bytes exceeds the limit of 8 bytes");
+    assert_eq!(interpreter.call_stack.global().members.get("s"), Some(&Value::String("abababab".to_string())));
+}
+
+#[test]
+fn values_larger_than_the_total_value_size_together_are_runtime_errors() {
+    let limits = Limits { max_total_value_size: Some(10), ..Limits::default() };
+    let interpreter = &mut Interpreter { limits, ..Interpreter::default() };
+    let source = "PROGRAM p; VAR a, b : STRING; \
+        PROCEDURE keep(s : STRING); BEGIN END; \
+        BEGIN a := 'abcdef'; a := 'ghijkl'; b := 'mnop'; keep(b); b := b + 'q' END.";
+    let error = run_program(interpreter, source).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Runtime);
+    assert_eq!(error.message, "values of 14 bytes exceed the total limit of 10 bytes");
+    assert_eq!(&source[error.span.start..error.span.end], "b");
+    assert_eq!(interpreter.call_stack.value_size(), 10);
+
+    let error = run_program(interpreter, "PROGRAM p; VAR a, b : STRING; BEGIN a := 'abcdef'; b := a + a END.").unwrap_err();
+    assert_eq!(error.message, "values of 18 bytes exceed the total limit of 10 bytes");
+}
+
+#[test]
+fn running_out_of_steps_is_a_budget_error() {
+    let interpreter = &mut Interpreter { limits: Limits { max_steps: Some(100), ..Limits::default() }, ..Interpreter::default() };
+    let source = "PROGRAM p; VAR i : INTEGER; BEGIN WHILE TRUE DO i := i + 1 END.";
+    let error = run_program(interpreter, source).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Budget);
+    assert_eq!(error.message, "step budget of 100 exhausted");
+    assert_eq!(interpreter.steps(), 101);
+
+    let source = "PROGRAM p; VAR i : INTEGER; BEGIN FOR i := 1 TO 10 DO END.";
+    assert_eq!(run_program(interpreter, source), Ok(None));
+}
+
+#[test]
+fn running_out_of_time_is_a_budget_error() {
+    let limits = Limits { time_limit: Some(Duration::from_millis(0)), ..Limits::default() };
+    let interpreter = &mut Interpreter { limits, ..Interpreter::default() };
+    let source = "PROGRAM p; VAR i : INTEGER; BEGIN REPEAT i := i + 1 UNTIL FALSE END.";
+    let error = run_program(interpreter, source).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Budget);
+    assert_eq!(error.message, "time limit of 0ns exceeded");
+}
+
+#[test]
+fn exceeding_the_live_variables_is_a_runtime_error_at_the_declaration_or_call() {
+    let limits = Limits { max_variables: Some(3), ..Limits::default() };
+    let interpreter = &mut Interpreter { limits, ..Interpreter::default() };
+    let source = "PROGRAM p; VAR a, b : INTEGER; \
+        PROCEDURE count(n : INTEGER); BEGIN IF n > 0 THEN count(n - 1) END; \
+        BEGIN count(0); count(1) END.";
+    let error = run_program(interpreter, source).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Runtime);
+    assert_eq!(error.message, "limit of 3 live variables exceeded");
+    assert_eq!(&source[error.span.start..error.span.end], "count(n - 1)");
+
+    let error = run_program(interpreter, "PROGRAM p; VAR a, b, c, d : INTEGER; BEGIN END.").unwrap_err();
+    assert_eq!(error.span, Span::new(24, 25, 1, 25));
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn big_integers_larger_than_the_value_size_are_runtime_errors() {
+    let limits = Limits { max_value_size: Some(16), ..Limits::default() };
+    let mut interpreter = Interpreter { limits, ..Interpreter::default() };
+    let result = |interpreter: &mut Interpreter, text: &str| {
+        interpreter.interpret(&crate::parser::parse(crate::lexer::lex(text).unwrap()).unwrap())
+    };
+
+    assert!(result(&mut interpreter, "2 ** 100").is_ok());
+    assert_eq!(result(&mut interpreter, "1 ** 2000000000"), Ok(Some(Value::Integer(1))));
+
+    let error = result(&mut interpreter, "2 ** 2000000000").unwrap_err();
+    assert_eq!(error.message, "value of 250000001 bytes exceeds the limit of 16 bytes");
+    assert_eq!(error.span, Span::new(2, 4, 1, 3));
+}
@@ -2,22 +2,84 @@ extern crate strcursor;
 
 use strcursor::StrCursor;
 use crate::types::*;
+use crate::error::*;
+use std::num::IntErrorKind;
+
+fn is_real_literal(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_digit()) && word.contains(&['.', 'e', 'E'][..])
+}
+
+// reserved words, matched regardless of case
+const KEYWORDS: &[(&str, Token)] = &[
+    ("PROGRAM", Token::PROGRAM),
+    ("VAR", Token::VAR),
+    ("PROCEDURE", Token::PROCEDURE),
+    ("FUNCTION", Token::FUNCTION),
+    ("INTEGER", Token::INTEGER),
+    ("REAL", Token::REAL),
+    ("BOOLEAN", Token::BOOLEAN),
+    ("STRING", Token::STRING),
+    ("BEGIN", Token::BEGIN),
+    ("END", Token::END),
+    ("IF", Token::IF),
+    ("THEN", Token::THEN),
+    ("ELSE", Token::ELSE),
+    ("WHILE", Token::WHILE),
+    ("DO", Token::DO),
+    ("REPEAT", Token::REPEAT),
+    ("UNTIL", Token::UNTIL),
+    ("FOR", Token::FOR),
+    ("TO", Token::TO),
+    ("DOWNTO", Token::DOWNTO),
+    ("TRUE", Token::TRUE),
+    ("FALSE", Token::FALSE),
+    ("DIV", Token::Operator(Operator::IntDiv)),
+    ("AND", Token::Operator(Operator::And)),
+    ("OR", Token::Operator(Operator::Or)),
+    ("NOT", Token::Operator(Operator::Not))
+];
+
+fn keyword(word: &str) -> Option<Token> {
+    KEYWORDS.iter()
+        .find(|(keyword, _)| word.eq_ignore_ascii_case(keyword))
+        .map(|(_, token)| token.clone())
+}
 
 fn tokenize_word(word: &str) -> Token {
     let integer: Result<i32, std::num::ParseIntError> = word.parse::<i32>();
 
     match integer {
         Ok(value) => Token::Number(Number::Integer(value)),
+        Err(_e) if is_real_literal(word) => {
+            match word.parse::<f64>() {
+                Ok(value) => Token::Number(Number::Real(value)),
+                Err(_e) => Token::Unknown
+            }
+        },
         Err(_e) => {
             match word {
                 "+" => Token::Operator(Operator::Add),
                 "-" => Token::Operator(Operator::Sub),
                 "*" => Token::Operator(Operator::Mul),
                 "/" => Token::Operator(Operator::Div),
+                "//" => Token::Operator(Operator::IntDiv),
+                "%" => Token::Operator(Operator::Mod),
+                "**" | "^" => Token::Operator(Operator::Pow),
+                "=" => Token::Operator(Operator::Equal),
+                "<>" => Token::Operator(Operator::NotEqual),
+                "<" => Token::Operator(Operator::Less),
+                "<=" => Token::Operator(Operator::LessEqual),
+                ">" => Token::Operator(Operator::Greater),
+                ">=" => Token::Operator(Operator::GreaterEqual),
                 "(" => Token::LPAREN,
                 ")" => Token::RPAREN,
-                " " => Token::Whitespace,
-                _ => Token::Unknown
+                ":=" => Token::ASSIGN,
+                ";" => Token::SEMI,
+                "." => Token::DOT,
+                ":" => Token::COLON,
+                "," => Token::COMMA,
+                " " | "\t" | "\n" | "\r\n" => Token::Whitespace,
+                _ => keyword(word).unwrap_or(Token::Unknown)
             }
         }
     }
@@ -26,56 +88,96 @@ fn tokenize_word(word: &str) -> Token {
 #[test]
 fn tokenize_word_returns_integer_number() {
     let word = "123";
-    let token = tokenize_word(&word);
+    let token = tokenize_word(word);
     assert_eq!(token, Token::Number(Number::Integer(123)));
 }
 
+#[test]
+fn tokenize_word_returns_real_number() {
+    assert_eq!(tokenize_word("1.5"), Token::Number(Number::Real(1.5)));
+    assert_eq!(tokenize_word("1.5e-3"), Token::Number(Number::Real(0.0015)));
+    assert_eq!(tokenize_word("2E3"), Token::Number(Number::Real(2000.0)));
+}
+
+#[test]
+fn tokenize_word_does_not_treat_words_as_real_numbers() {
+    assert_eq!(tokenize_word("inf"), Token::Unknown);
+    assert_eq!(tokenize_word("e5"), Token::Unknown);
+}
+
 #[test]
 fn tokenize_word_returns_add_operator() {
     let word = "+";
-    let token = tokenize_word(&word);
+    let token = tokenize_word(word);
     assert_eq!(token, Token::Operator(Operator::Add));
 }
 
 #[test]
 fn tokenize_word_returns_sub_operator() {
     let word = "-";
-    let token = tokenize_word(&word);
+    let token = tokenize_word(word);
     assert_eq!(token, Token::Operator(Operator::Sub));
 }
 
 #[test]
 fn tokenize_word_returns_mul_operator() {
     let word = "*";
-    let token = tokenize_word(&word);
+    let token = tokenize_word(word);
     assert_eq!(token, Token::Operator(Operator::Mul));
 }
 
 #[test]
 fn tokenize_word_returns_div_operator() {
     let word = "/";
-    let token = tokenize_word(&word);
+    let token = tokenize_word(word);
     assert_eq!(token, Token::Operator(Operator::Div));
 }
 
+#[test]
+fn tokenize_word_returns_integer_division_operator() {
+    assert_eq!(tokenize_word("//"), Token::Operator(Operator::IntDiv));
+    assert_eq!(tokenize_word("DIV"), Token::Operator(Operator::IntDiv));
+    assert_eq!(tokenize_word("div"), Token::Operator(Operator::IntDiv));
+}
+
+#[test]
+fn tokenize_word_returns_mod_and_pow_operators() {
+    assert_eq!(tokenize_word("%"), Token::Operator(Operator::Mod));
+    assert_eq!(tokenize_word("**"), Token::Operator(Operator::Pow));
+    assert_eq!(tokenize_word("^"), Token::Operator(Operator::Pow));
+}
+
+#[test]
+fn tokenize_word_returns_keywords_regardless_of_case() {
+    assert_eq!(tokenize_word("PROGRAM"), Token::PROGRAM);
+    assert_eq!(tokenize_word("Begin"), Token::BEGIN);
+    assert_eq!(tokenize_word("end"), Token::END);
+}
+
 #[test]
 fn tokenize_word_returns_whitespace() {
     let word = " ";
-    let token = tokenize_word(&word);
+    let token = tokenize_word(word);
     assert_eq!(token, Token::Whitespace);
 }
 
+#[test]
+fn tokenize_word_returns_whitespace_for_tabs_and_newlines() {
+    assert_eq!(tokenize_word("\t"), Token::Whitespace);
+    assert_eq!(tokenize_word("\n"), Token::Whitespace);
+}
+
 #[test]
 fn tokenize_word_returns_left_paren() {
     let word = "(";
-    let token = tokenize_word(&word);
+    let token = tokenize_word(word);
     assert_eq!(token, Token::LPAREN);
 }
 
 #[test]
 fn tokenize_word_returns_right_paren() {
     let word = ")";
-    let token = tokenize_word(&word);
+    let token = tokenize_word(word);
     assert_eq!(token, Token::RPAREN);
 }
 
@@ -91,9 +193,9 @@ fn is_integer_returns_correctly() {
     let float_string = "1.23";
     let string = "abc";
 
-    assert_eq!(is_integer(integer_string), true);
-    assert_eq!(is_integer(float_string), false);
-    assert_eq!(is_integer(string), false);
+    assert!(is_integer(integer_string));
+    assert!(!is_integer(float_string));
+    assert!(!is_integer(string));
 }
 
 fn parse_digits<'a>(cur: &mut strcursor::StrCursor, number_string: &'a mut String) -> &'a str {
@@ -139,27 +241,325 @@ fn parse_digits_handles_three_digit_integers() {
     assert_eq!(integer_string, "123");
 }
 
-fn tokenize(text: &str, tokens: &[Token]) -> Vec<Token> {
+fn peek<'a>(cur: &StrCursor<'a>) -> Option<&'a str> {
+    cur.after().map(|gc| gc.as_str())
+}
+
+fn starts_with_digit(cur: &StrCursor) -> bool {
+    peek(cur).is_some_and(is_integer)
+}
+
+// digits, followed by an optional fraction (`.5`) and an optional exponent (`e-3`);
+// the fraction and exponent are only consumed if they are followed by digits
+fn parse_number<'a>(cur: &mut StrCursor, number_string: &'a mut String) -> &'a str {
+    parse_digits(cur, number_string);
+
+    let mut lookahead = *cur;
+    if let Some(".") = peek(&lookahead) {
+        lookahead.seek_next();
+
+        if starts_with_digit(&lookahead) {
+            number_string.push('.');
+            *cur = lookahead;
+            parse_digits(cur, number_string);
+        }
+    }
+
+    let mut lookahead = *cur;
+    if let Some(exponent @ "e") | Some(exponent @ "E") = peek(&lookahead) {
+        let mut exponent = exponent.to_string();
+        lookahead.seek_next();
+
+        if let Some(sign @ "+") | Some(sign @ "-") = peek(&lookahead) {
+            exponent.push_str(sign);
+            lookahead.seek_next();
+        }
+
+        if starts_with_digit(&lookahead) {
+            number_string.push_str(&exponent);
+            *cur = lookahead;
+            parse_digits(cur, number_string);
+        }
+    }
+
+    number_string
+}
+
+#[test]
+fn parse_number_handles_integers() {
+    let mut cur = StrCursor::new_at_start("123+"); 
+    assert_eq!(parse_number(&mut cur, &mut String::new()), "123");
+    assert_eq!(peek(&cur), Some("+"));
+}
+
+#[test]
+fn parse_number_handles_fractions_and_exponents() {
+    let mut cur = StrCursor::new_at_start("1.5e-3 "); 
+    assert_eq!(parse_number(&mut cur, &mut String::new()), "1.5e-3");
+    assert_eq!(peek(&cur), Some(" "));
+}
+
+#[test]
+fn parse_number_leaves_incomplete_fractions_and_exponents_alone() {
+    let mut cur = StrCursor::new_at_start("1.+"); 
+    assert_eq!(parse_number(&mut cur, &mut String::new()), "1");
+    assert_eq!(peek(&cur), Some("."));
+
+    let mut cur = StrCursor::new_at_start("2e+"); 
+    assert_eq!(parse_number(&mut cur, &mut String::new()), "2");
+    assert_eq!(peek(&cur), Some("e"));
+}
+
+// like `tokenize_word`, but explains why a digit run is not a valid number;
+// with the `bignum` feature integer literals that do not fit into an Integer are
+// promoted to BigInteger instead
+fn tokenize_number(word: &str, span: Span) -> Result<Token, Error> {
+    match tokenize_word(word) {
+        Token::Number(Number::Real(value)) if value.is_infinite() => Err(
+            Error::lexical("real literal out of range", span)
+                .with_note(format!("the largest Real is {:e}", f64::MAX))
+        ),
+        Token::Unknown => match word.parse::<i32>() {
+            #[cfg(feature = "bignum")]
+            Err(ref error) if *error.kind() == IntErrorKind::PosOverflow => {
+                Ok(Token::Number(Number::BigInteger(word.parse().unwrap())))
+            },
+            #[cfg(not(feature = "bignum"))]
+            Err(ref error) if *error.kind() == IntErrorKind::PosOverflow && word.parse::<i64>() == Ok(-i64::from(i32::MIN)) => {
+                Ok(Token::Number(Number::MinIntegerMagnitude))
+            },
+            #[cfg(not(feature = "bignum"))]
+            Err(ref error) if *error.kind() == IntErrorKind::PosOverflow => Err(
+                Error::lexical("integer literal out of range", span)
+                    .with_note(format!("the largest Integer is {}", i32::MAX))
+            ),
+            _ => Err(Error::lexical(format!("invalid number literal \"{}\"", word), span))
+        },
+        token => Ok(token)
+    }
+}
+
+#[cfg(not(feature = "bignum"))]
+#[test]
+fn tokenize_number_reports_integer_literals_out_of_range() {
+    let span = Span::new(0, 11, 1, 1);
+    let error = tokenize_number("99999999999", span).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Lexical);
+    assert_eq!(error.message, "integer literal out of range");
+    assert_eq!(error.span, span);
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn tokenize_number_promotes_integer_literals_out_of_range() {
+    let token = tokenize_number("99999999999", Span::new(0, 11, 1, 1));
+
+    assert_eq!(token, Ok(Token::Number(Number::BigInteger(99999999999u64.into()))));
+}
+
+#[cfg(not(feature = "bignum"))]
+#[test]
+fn tokenize_number_keeps_the_digits_of_the_smallest_integer() {
+    let span = Span::new(0, 10, 1, 1);
+
+    assert_eq!(tokenize_number("2147483648", span), Ok(Token::Number(Number::MinIntegerMagnitude)));
+    assert_eq!(tokenize_number("2147483649", span).unwrap_err().message, "integer literal out of range");
+}
+
+#[test]
+fn tokenize_number_reports_real_literals_out_of_range() {
+    let error = tokenize_number("1e999", Span::new(0, 5, 1, 1)).unwrap_err();
+
+    assert_eq!(error.message, "real literal out of range");
+}
+
+#[test]
+fn tokenize_number_accepts_the_largest_integer() {
+    assert_eq!(
+        tokenize_number("2147483647", Span::new(0, 10, 1, 1)),
+        Ok(Token::Number(Number::Integer(i32::MAX)))
+    );
+}
+
+// operators spelled with more than one character
+const MULTI_CHARACTER_SYMBOLS: &[&str] = &["**", "//", ":=", "<>", "<=", ">="];
+
+fn is_word_start(c: &str) -> bool {
+    c.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+fn is_word_character(c: &str) -> bool {
+    c.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+}
+
+fn parse_word<'a>(cur: &mut StrCursor, word: &'a mut String) -> &'a str {
+    while let Some(c) = peek(cur) {
+        if !is_word_character(c) {
+            break
+        }
+
+        word.push_str(c);
+        cur.seek_next();
+    }
+
+    word
+}
+
+#[test]
+fn parse_word_stops_at_the_first_non_word_character() {
+    let mut cur = StrCursor::new_at_start("div_2 + 1"); 
+
+    assert_eq!(parse_word(&mut cur, &mut String::new()), "div_2");
+    assert_eq!(peek(&cur), Some(" "));
+}
+
+fn grapheme_count(text: &str) -> usize {
+    let mut cur = StrCursor::new_at_start(text);
+    let mut count = 0;
+
+    while cur.after().is_some() {
+        cur.seek_next();
+        count += 1;
+    }
+
+    count
+}
+
+fn is_newline(c: &str) -> bool {
+    c == "\n" || c == "\r\n"
+}
+
+// the contents of the string literal at the cursor, with every `''` unescaped
+// to a single quote; `None` if the literal is not closed on the same line
+fn parse_string(cur: &mut StrCursor) -> Option<String> {
+    let mut string = String::new();
+    cur.seek_next();
+
+    loop {
+        match peek(cur) {
+            Some("'") => {
+                cur.seek_next();
+
+                if peek(cur) != Some("'") {
+                    return Some(string)
+                }
+
+                string.push('\'');
+                cur.seek_next();
+            },
+            Some(c) if !is_newline(c) => {
+                string.push_str(c);
+                cur.seek_next();
+            },
+            _ => return None
+        }
+    }
+}
+
+#[test]
+fn parse_string_unescapes_doubled_quotes() {
+    let mut cur = StrCursor::new_at_start("'it''s' + s");
+
+    assert_eq!(parse_string(&mut cur), Some("it's".to_string()));
+    assert_eq!(peek(&cur), Some(" "));
+}
+
+#[test]
+fn parse_string_stops_at_the_end_of_the_line() {
+    assert_eq!(parse_string(&mut StrCursor::new_at_start("'abc\n'")), None);
+    assert_eq!(parse_string(&mut StrCursor::new_at_start("'abc''")), None);
+}
+
+fn tokenize(text: &str, tokens: &[Spanned<Token>]) -> Result<Vec<Spanned<Token>>, Error> {
     let mut tokens = tokens.to_owned();
 
     let mut cur = StrCursor::new_at_start(text); 
+    let mut line = 1;
+    let mut column = 1;
 
     while let Some(gc) = cur.after() {
-        if is_integer(&gc.to_string()) {
-            tokens.push(tokenize_word(&parse_digits(&mut cur, &mut String::new())));
+        let grapheme: &str = gc.as_ref();
+        let start = cur.byte_pos();
+
+        if is_integer(grapheme) {
+            let word = parse_number(&mut cur, &mut String::new()).to_string();
+            let end = cur.byte_pos();
+            let span = Span::new(start, end, line, column);
+
+            tokens.push(Spanned::new(tokenize_number(&word, span)?, span));
+            column += end - start;
+        } else if is_word_start(grapheme) {
+            let word = parse_word(&mut cur, &mut String::new()).to_string();
+            let end = cur.byte_pos();
+            let span = Span::new(start, end, line, column);
+
+            let token = match tokenize_word(&word) {
+                // every word that is not a keyword names a variable
+                Token::Unknown => Token::Id(word.clone()),
+                token => token
+            };
+
+            tokens.push(Spanned::new(token, span));
+            column += grapheme_count(&word);
+        } else if grapheme == "'" {
+            let string = parse_string(&mut cur);
+            let end = cur.byte_pos();
+            let span = Span::new(start, end, line, column);
+
+            match string {
+                Some(string) => tokens.push(Spanned::new(Token::String(string), span)),
+                None => return Err(
+                    Error::lexical("unterminated string literal", span)
+                        .with_note("string literals end on the line they start on, a quote inside one is written `''`")
+                )
+            }
+
+            column += grapheme_count(&text[start..end]);
+        } else if let Some(symbol) = MULTI_CHARACTER_SYMBOLS.iter().find(|symbol| cur.slice_after().starts_with(*symbol)) {
+            let span = Span::new(start, start + symbol.len(), line, column);
+
+            tokens.push(Spanned::new(tokenize_word(symbol), span));
+            cur = StrCursor::new_at_left_of_byte_pos(text, span.end);
+            column += grapheme_count(symbol);
         } else {
-            tokens.push(tokenize_word(&gc.to_string()));
-            cur.seek_next()
+            let token = tokenize_word(grapheme);
+            let span = Span::new(start, start + grapheme.len(), line, column);
+
+            if let Token::Unknown = token {
+                return Err(Error::lexical(
+                    format!("unexpected character \"{}\"", grapheme),
+                    span
+                ))
+            }
+
+            tokens.push(Spanned::new(token, span));
+            cur.seek_next();
+
+            if is_newline(grapheme) {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
         }
     }
 
-    tokens
+    let end = text.len();
+    tokens.push(Spanned::new(Token::EOF, Span::new(end, end, line, column)));
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+fn token_values(tokens: Vec<Spanned<Token>>) -> Vec<Token> {
+    tokens.into_iter().map(|token| token.value).collect()
 }
 
 #[test]
 fn tokenize_returns_a_vector_of_tokens() {
     let expr = "2 / 321 - 44 * 31";
-    let tokens = tokenize(&expr, &Vec::new());
+    let tokens = token_values(tokenize(expr, &Vec::new()).unwrap());
 
     assert_eq!(tokens, [
         Token::Number(Number::Integer(2)),
@@ -174,18 +574,48 @@ fn tokenize_returns_a_vector_of_tokens() {
         Token::Whitespace,
         Token::Operator(Operator::Mul),
         Token::Whitespace,
-        Token::Number(Number::Integer(31))
+        Token::Number(Number::Integer(31)),
+        Token::EOF
     ]);
 }
 
-pub fn lex(text: &str) -> Vec<Token> {
+#[test]
+fn tokenize_returns_lexical_error_on_unknown_character() {
+    let expr = "2 + $";
+    let error = tokenize(expr, &Vec::new()).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Lexical);
+    assert_eq!(error.span, Span::new(4, 5, 1, 5));
+}
+
+#[test]
+fn tokenize_attaches_spans_to_tokens() {
+    let expr = "12 +\n(3)";
+    let spans: Vec<Span> = tokenize(expr, &Vec::new()).unwrap()
+        .into_iter()
+        .map(|token| token.span)
+        .collect();
+
+    assert_eq!(spans, [
+        Span::new(0, 2, 1, 1),
+        Span::new(2, 3, 1, 3),
+        Span::new(3, 4, 1, 4),
+        Span::new(4, 5, 1, 5),
+        Span::new(5, 6, 2, 1),
+        Span::new(6, 7, 2, 2),
+        Span::new(7, 8, 2, 3),
+        Span::new(8, 8, 2, 4)
+    ]);
+}
+
+pub fn lex(text: &str) -> Result<Vec<Spanned<Token>>, Error> {
     tokenize(text, &Vec::new())
 }
 
 #[test]
 fn lex_returns_a_vector_of_tokens() {
     let expr = "2 + 3 * 4";
-    let tokens = lex(expr);
+    let tokens = token_values(lex(expr).unwrap());
 
     assert_eq!(tokens, [
         Token::Number(Number::Integer(2)),
@@ -196,6 +626,173 @@ fn lex_returns_a_vector_of_tokens() {
         Token::Whitespace,
         Token::Operator(Operator::Mul),
         Token::Whitespace,
-        Token::Number(Number::Integer(4))
+        Token::Number(Number::Integer(4)),
+        Token::EOF
     ]);
-}
\ No newline at end of file
+}
+
+#[test]
+fn lex_places_eof_after_the_last_token() {
+    let tokens = lex("1 + 23").unwrap();
+
+    assert_eq!(tokens.last(), Some(&Spanned::new(Token::EOF, Span::new(6, 6, 1, 7))));
+}
+
+#[test]
+fn lex_returns_real_numbers() {
+    let tokens = token_values(lex("1.5 * 2e2").unwrap());
+
+    assert_eq!(tokens, [
+        Token::Number(Number::Real(1.5)),
+        Token::Whitespace,
+        Token::Operator(Operator::Mul),
+        Token::Whitespace,
+        Token::Number(Number::Real(200.0)),
+        Token::EOF
+    ]);
+}
+
+#[cfg(not(feature = "bignum"))]
+#[test]
+fn lex_reports_the_span_of_integer_literals_out_of_range() {
+    let error = lex("1 + 99999999999").unwrap_err();
+
+    assert_eq!(error.message, "integer literal out of range");
+    assert_eq!(error.span, Span::new(4, 15, 1, 5));
+}
+
+#[test]
+fn lex_returns_multi_character_operators() {
+    let tokens = token_values(lex("2**3 // 4 div 5").unwrap());
+
+    assert_eq!(tokens, [
+        Token::Number(Number::Integer(2)),
+        Token::Operator(Operator::Pow),
+        Token::Number(Number::Integer(3)),
+        Token::Whitespace,
+        Token::Operator(Operator::IntDiv),
+        Token::Whitespace,
+        Token::Number(Number::Integer(4)),
+        Token::Whitespace,
+        Token::Operator(Operator::IntDiv),
+        Token::Whitespace,
+        Token::Number(Number::Integer(5)),
+        Token::EOF
+    ]);
+}
+
+#[test]
+fn lex_returns_comparison_and_logical_operators() {
+    let tokens = token_values(lex("a<=b<>c>=d<e>f=TRUE and not false").unwrap());
+
+    assert_eq!(tokens, [
+        Token::Id("a".to_string()),
+        Token::Operator(Operator::LessEqual),
+        Token::Id("b".to_string()),
+        Token::Operator(Operator::NotEqual),
+        Token::Id("c".to_string()),
+        Token::Operator(Operator::GreaterEqual),
+        Token::Id("d".to_string()),
+        Token::Operator(Operator::Less),
+        Token::Id("e".to_string()),
+        Token::Operator(Operator::Greater),
+        Token::Id("f".to_string()),
+        Token::Operator(Operator::Equal),
+        Token::TRUE,
+        Token::Whitespace,
+        Token::Operator(Operator::And),
+        Token::Whitespace,
+        Token::Operator(Operator::Not),
+        Token::Whitespace,
+        Token::FALSE,
+        Token::EOF
+    ]);
+}
+
+#[test]
+fn lex_keeps_columns_after_multi_character_tokens() {
+    let spans: Vec<Span> = lex("2**3 div 5").unwrap().into_iter().map(|token| token.span).collect();
+
+    assert_eq!(spans[1], Span::new(1, 3, 1, 2));
+    assert_eq!(spans[4], Span::new(5, 8, 1, 6));
+    assert_eq!(spans[6], Span::new(9, 10, 1, 10));
+}
+
+#[test]
+fn lex_returns_identifiers_and_assignments() {
+    let tokens = lex("x_1:=y").unwrap();
+
+    assert_eq!(token_values(tokens.clone()), [
+        Token::Id("x_1".to_string()),
+        Token::ASSIGN,
+        Token::Id("y".to_string()),
+        Token::EOF
+    ]);
+    assert_eq!(tokens[1].span, Span::new(3, 5, 1, 4));
+}
+
+#[test]
+fn lex_separates_the_final_dot_from_numbers() {
+    let tokens = token_values(lex("x := 1; END.").unwrap());
+
+    assert_eq!(tokens, [
+        Token::Id("x".to_string()),
+        Token::Whitespace,
+        Token::ASSIGN,
+        Token::Whitespace,
+        Token::Number(Number::Integer(1)),
+        Token::SEMI,
+        Token::Whitespace,
+        Token::END,
+        Token::DOT,
+        Token::EOF
+    ]);
+}
+
+#[test]
+fn lex_returns_string_literals() {
+    let tokens = lex("s := 'don''t' + '';").unwrap();
+
+    assert_eq!(token_values(tokens.clone()), [
+        Token::Id("s".to_string()),
+        Token::Whitespace,
+        Token::ASSIGN,
+        Token::Whitespace,
+        Token::String("don't".to_string()),
+        Token::Whitespace,
+        Token::Operator(Operator::Add),
+        Token::Whitespace,
+        Token::String(String::new()),
+        Token::SEMI,
+        Token::EOF
+    ]);
+    assert_eq!(tokens[4].span, Span::new(5, 13, 1, 6));
+    assert_eq!(tokens[6].span, Span::new(14, 15, 1, 15));
+}
+
+#[test]
+fn lex_reports_unterminated_string_literals() {
+    let error = lex("x := 'abc\ny := 1").unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Lexical);
+    assert_eq!(error.message, "unterminated string literal");
+    assert_eq!(error.span, Span::new(5, 9, 1, 6));
+}
+
+#[test]
+fn lex_returns_variable_declarations() {
+    let tokens = token_values(lex("VAR a,b: Integer;").unwrap());
+
+    assert_eq!(tokens, [
+        Token::VAR,
+        Token::Whitespace,
+        Token::Id("a".to_string()),
+        Token::COMMA,
+        Token::Id("b".to_string()),
+        Token::COLON,
+        Token::Whitespace,
+        Token::INTEGER,
+        Token::SEMI,
+        Token::EOF
+    ]);
+}
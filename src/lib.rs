@@ -0,0 +1,13 @@
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod ast;
+pub mod diagnostics;
+pub mod error;
+pub mod types;
+pub mod lexer;
+pub mod parser;
+pub mod builtins;
+pub mod semantic;
+pub mod interpreter;
+pub mod utils;
+pub mod value;
@@ -1,30 +1,165 @@
-/**
- *
- * TODO:
- *
- * - handle div/mul cases with % (handle floating point calculation)
- * - syntax error for LPAREN w/o expr or RPAREN
- * - syntax error for RPAREN w/o opening LPAREN
- *
-*/
-
+use std::env;
 use std::io;
+use std::io::IsTerminal;
+use std::io::Read;
+use std::process;
+use std::str::FromStr;
+use std::time::Duration;
+
+use ast_interpreter::{lexer, parser, semantic};
+use ast_interpreter::interpreter::{DivisionMode, Interpreter, OverflowMode};
+use ast_interpreter::diagnostics::{self, ColorMode};
+use ast_interpreter::error::Error;
+use ast_interpreter::types::{is_whitespace, Token};
+use ast_interpreter::value::Value;
+
+fn run(interpreter: &mut Interpreter, expr: &str) -> Result<Option<Value>, Error> {
+    let tokens = lexer::lex(expr)?;
+    let ast = parser::parse_with_max_depth(tokens, interpreter.limits.max_nesting_depth)?;
+
+    interpreter.interpret(&ast)
+}
+
+// like `run`, but checks the whole program before running any of it; returns
+// the warnings found while checking
+fn run_program(interpreter: &mut Interpreter, source: &str) -> Result<Vec<Error>, Vec<Error>> {
+    let tokens = lexer::lex(source).map_err(|error| vec![error])?;
+    let max_depth = interpreter.limits.max_nesting_depth;
+    let mut ast = parser::parse_with_max_depth(tokens, max_depth).map_err(|error| vec![error])?;
+
+    let warnings = semantic::analyze(&ast)?;
+    semantic::check(&mut ast)?;
+    interpreter.interpret(&ast).map_err(|error| vec![error])?;
+
+    Ok(warnings)
+}
+
+#[derive(Default)]
+struct Options {
+    interpreter: Interpreter,
+    // print Rationals as decimals instead of fractions
+    decimal: bool
+}
+
+// the value of an argument like `--max-steps=1000`
+fn number<T: FromStr>(argument: &str) -> T {
+    let value = argument.split_once('=').map_or("", |(_, value)| value);
+
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("invalid number in argument: {}", argument);
+        process::exit(2);
+    })
+}
+
+fn options() -> Options {
+    let mut options = Options::default();
 
-mod ast;
-mod types;
-mod lexer;
-mod parser;
-mod interpreter;
-mod utils;
+    for argument in env::args().skip(1) {
+        match argument.as_str() {
+            "--checked" => options.interpreter.overflow_mode = OverflowMode::Checked,
+            "--wrapping" => options.interpreter.overflow_mode = OverflowMode::Wrapping,
+            "--saturating" => options.interpreter.overflow_mode = OverflowMode::Saturating,
+            "--exact" => options.interpreter.division_mode = DivisionMode::Exact,
+            "--decimal" => options.decimal = true,
+            "--trace-calls" => options.interpreter.trace_calls = true,
+            argument if argument.starts_with("--max-call-depth=") => {
+                options.interpreter.limits.max_call_depth = number(argument);
+            },
+            argument if argument.starts_with("--max-steps=") => {
+                options.interpreter.limits.max_steps = Some(number(argument));
+            },
+            argument if argument.starts_with("--time-limit-ms=") => {
+                options.interpreter.limits.time_limit = Some(Duration::from_millis(number(argument)));
+            },
+            argument if argument.starts_with("--max-variables=") => {
+                options.interpreter.limits.max_variables = Some(number(argument));
+            },
+            argument if argument.starts_with("--max-value-size=") => {
+                options.interpreter.limits.max_value_size = Some(number(argument));
+            },
+            argument if argument.starts_with("--max-total-value-size=") => {
+                options.interpreter.limits.max_total_value_size = Some(number(argument));
+            },
+            _ => {
+                eprintln!("unknown argument: {}", argument);
+                process::exit(2);
+            }
+        }
+    }
+
+    options
+}
+
+fn color_mode() -> ColorMode {
+    if env::var_os("NO_COLOR").is_none() && io::stderr().is_terminal() {
+        ColorMode::Ansi
+    } else {
+        ColorMode::Plain
+    }
+}
+
+// whether `source` is a whole program rather than a sequence of lines
+fn is_program(source: &str) -> bool {
+    lexer::lex(source).is_ok_and(|tokens| {
+        tokens.iter().find(|token| !is_whitespace(&token.value)).is_some_and(|token| token.value == Token::PROGRAM)
+    })
+}
+
+fn report(error: &Error, source: &str) {
+    eprint!("{}", diagnostics::render(error, source, color_mode()));
+}
+
+fn format_value(options: &Options, value: &Value) -> String {
+    if options.decimal { value.to_decimal().to_string() } else { value.to_string() }
+}
 
 fn main() {
-    let buffer = &mut String::new();
-
-    if io::stdin().read_line(buffer).is_ok() {
-        let expr = buffer.trim_end();
-        let tokens = lexer::lex(expr);
-        let ast = parser::parse(tokens);
-        let result = interpreter::interpret(&ast);
-        println!("= {}", result);
+    let mut options = options();
+    let mut source = String::new();
+
+    if io::stdin().read_to_string(&mut source).is_err() {
+        process::exit(1);
+    }
+
+    // a program runs as a whole and prints its variables once it is done
+    if is_program(&source) {
+        match run_program(&mut options.interpreter, &source) {
+            Ok(warnings) => warnings.iter().for_each(|warning| report(warning, &source)),
+            Err(errors) => {
+                errors.iter().for_each(|error| report(error, &source));
+                process::exit(1);
+            }
+        }
+
+        for (name, value) in &options.interpreter.call_stack.global().members {
+            println!("{} = {}", name, format_value(&options, value));
+        }
+
+        return
+    }
+
+    // otherwise every line is one statement, variables assigned on one line
+    // stay defined for the following lines
+    let mut failed = false;
+
+    for line in source.lines() {
+        let expr = line.trim_end();
+
+        if expr.trim_start().is_empty() {
+            continue
+        }
+
+        match run(&mut options.interpreter, expr) {
+            Ok(Some(result)) => println!("= {}", format_value(&options, &result)),
+            Ok(None) => {},
+            Err(error) => {
+                report(&error, expr);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
     }
 }
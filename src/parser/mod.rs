@@ -1,142 +1,664 @@
+use std::sync::Arc;
+
 use crate::types::*;
 use crate::ast::*;
 use crate::utils::*;
+use crate::error::*;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Associativity {
+    Left,
+    Right
+}
+
+// binding power of the binary operators, higher binds tighter; an operator
+// becomes part of the expression grammar by registering it here
+//
+// as in Pascal, OR and AND bind like addition and multiplication, tighter than
+// comparisons, so `a < b AND c < d` needs parentheses around the comparisons
+pub const BINARY_OPERATORS: &[(Operator, u8, Associativity)] = &[
+    (Operator::Equal, 5, Associativity::Left),
+    (Operator::NotEqual, 5, Associativity::Left),
+    (Operator::Less, 5, Associativity::Left),
+    (Operator::LessEqual, 5, Associativity::Left),
+    (Operator::Greater, 5, Associativity::Left),
+    (Operator::GreaterEqual, 5, Associativity::Left),
+    (Operator::Add, 10, Associativity::Left),
+    (Operator::Sub, 10, Associativity::Left),
+    (Operator::Or, 10, Associativity::Left),
+    (Operator::Mul, 20, Associativity::Left),
+    (Operator::Div, 20, Associativity::Left),
+    (Operator::IntDiv, 20, Associativity::Left),
+    (Operator::Mod, 20, Associativity::Left),
+    (Operator::And, 20, Associativity::Left),
+    (Operator::Pow, 40, Associativity::Right)
+];
+
+// prefix operators bind their operand with the given binding power, so `-2 * 3`
+// parses as `(-2) * 3` but `-2 ** 2` as `-(2 ** 2)`
+pub const PREFIX_OPERATORS: &[(Operator, u8)] = &[
+    (Operator::Add, 30),
+    (Operator::Sub, 30),
+    (Operator::Not, 30)
+];
+
+// how deeply expressions, statements and declarations may nest, the parser and
+// every pass over the tree recurse once per level; unoptimized builds take up
+// to 12 KiB per level, so this fits into the 2 MiB stack of a spawned thread
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 128;
+
+fn binary_operator(token: &Token) -> Option<(u8, Associativity)> {
+    match token {
+        Token::Operator(operator) => BINARY_OPERATORS.iter()
+            .find(|(registered, _, _)| registered == operator)
+            .map(|&(_, precedence, associativity)| (precedence, associativity)),
+        _ => None
+    }
+}
+
+fn prefix_operator(token: &Token) -> Option<u8> {
+    match token {
+        Token::Operator(operator) => PREFIX_OPERATORS.iter()
+            .find(|(registered, _)| registered == operator)
+            .map(|&(_, precedence)| precedence),
+        _ => None
+    }
+}
 
 #[derive(Debug)]
 struct Parser {
     current_token_index: usize,
-    tokens: Vec<Token>
+    tokens: Vec<Spanned<Token>>,
+    // levels of nesting around the current token
+    depth: usize,
+    max_depth: usize
 }
 
 impl Parser {
+    fn new(tokens: Vec<Spanned<Token>>, max_depth: usize) -> Parser {
+        Parser { current_token_index: 0, tokens, depth: 0, max_depth }
+    }
+
+    // one level deeper, for the node starting at `span`; the caller subtracts
+    // it from `depth` again once the node is parsed
+    fn enter(&mut self, span: Span) -> Result<(), Error> {
+        if self.depth == self.max_depth {
+            return Err(
+                Error::syntax(format!("maximum nesting depth of {} exceeded", self.max_depth), span)
+                    .with_note("split the expression or statement into smaller ones")
+            )
+        }
+
+        self.depth += 1;
+
+        Ok(())
+    }
+
     fn next_token(&mut self) {
         self.current_token_index = clamp(self.current_token_index + 1, 0, self.tokens.len() - 1);
     }
 
-    fn eat(&mut self, token: Token) -> Token {
-        let current_token = self.tokens[self.current_token_index];
+    fn current_token(&self) -> Spanned<Token> {
+        self.tokens[self.current_token_index].clone()
+    }
 
-        if token != current_token {
-            panic!("Syntax Error: expected {}, found {}", token, current_token)
+    fn eat(&mut self, token: Token) -> Result<Spanned<Token>, Error> {
+        let current_token = self.current_token();
+
+        if token != current_token.value {
+            return Err(Error::syntax(
+                format!("expected {}, found {}", token, current_token.value),
+                current_token.span
+            ))
         }
 
         self.next_token();
 
-        current_token
+        Ok(current_token)
     }
 
     fn skip_whitespace(&mut self) {
-        if let Token::Whitespace = self.tokens[self.current_token_index] {
+        while is_whitespace(&self.tokens[self.current_token_index].value) {
             self.current_token_index += 1;
         }
     }
 
-    fn factor(&mut self) -> Node {
+    // first token after the current one that is not whitespace
+    fn peek_token(&self) -> Token {
+        self.tokens[self.current_token_index + 1..].iter()
+            .map(|token| &token.value)
+            .find(|token| !is_whitespace(token))
+            .cloned()
+            .unwrap_or(Token::EOF)
+    }
+
+    fn variable(&mut self) -> Result<Spanned<String>, Error> {
         self.skip_whitespace();
 
-        let token = self.tokens[self.current_token_index];
+        let token = self.current_token();
 
-        match token {
-            Token::Number(Number::Integer(value)) => {
-                self.eat(Token::Number(Number::Integer(value)));
+        match token.value {
+            Token::Id(name) => {
+                self.next_token();
 
-                Node::Token(token)
+                Ok(Spanned::new(name, token.span))
             },
+            _ => Err(Error::syntax(format!("expected Id, found {}", token.value), token.span))
+        }
+    }
+
+    fn factor(&mut self) -> Result<Node, Error> {
+        self.skip_whitespace();
+
+        let token = self.current_token();
+
+        if let Some(precedence) = prefix_operator(&token.value) {
+            self.eat(token.value.clone())?;
+            self.enter(token.span)?;
+
+            let expr = self.binary_expression(precedence)?;
+            self.depth -= 1;
+
+            return Ok(Node::UnaryOperator(Box::new(UnaryOperator { token, expr })))
+        }
+
+        match &token.value {
+            Token::Number(number) => {
+                self.eat(Token::Number(number.clone()))?;
+
+                Ok(Node::Token(token))
+            },
+            Token::TRUE | Token::FALSE | Token::String(_) => {
+                self.next_token();
+
+                Ok(Node::Token(token))
+            },
+            Token::Id(_) if self.peek_token() == Token::LPAREN => self.procedure_call(),
+            Token::Id(_) => Ok(Node::Var(self.variable()?)),
             Token::LPAREN => {
-                self.eat(Token::LPAREN);
+                let lparen = self.eat(Token::LPAREN)?;
+                self.enter(lparen.span)?;
 
-                let node = self.expr(); 
+                let node = self.expr()?;
+                self.depth -= 1;
 
-                self.eat(Token::RPAREN);
+                self.skip_whitespace();
+                self.eat(Token::RPAREN)
+                    .map_err(|error| error.with_label(lparen.span, "unclosed `(` opened here"))?;
 
-                node
+                Ok(node)
             },
-            _ => panic!(format!("Syntax Error: expected Integer or \"(\", found {}", token))
+            _ => Err(Error::syntax(
+                format!("expected Number, String, TRUE, FALSE, Id, \"+\", \"-\", NOT or \"(\", found {}", token.value),
+                token.span
+            ))
         }
     }
 
-    fn expr(&mut self) -> Node {
-        let mut node = self.term();
+    // precedence climbing: folds operators binding at least as tight as
+    // `min_precedence` into the left operand
+    //
+    // every operator folded in nests the left operand one level deeper
+    fn binary_expression(&mut self, min_precedence: u8) -> Result<Node, Error> {
+        let mut node = self.factor()?;
+        let depth = self.depth;
+
+        loop {
+            self.skip_whitespace();
+
+            let token = self.current_token();
+            let (precedence, associativity) = match binary_operator(&token.value) {
+                Some((precedence, associativity)) if precedence >= min_precedence => (precedence, associativity),
+                _ => break
+            };
+
+            self.eat(token.value.clone())?;
+            self.enter(token.span)?;
+
+            let right_precedence = match associativity {
+                Associativity::Left => precedence + 1,
+                Associativity::Right => precedence
+            };
+
+            node = Node::BinaryOperator(Box::new(BinaryOperator {
+                left: node,
+                token,
+                right: self.binary_expression(right_precedence)?,
+                type_spec: None
+            }))
+        }
 
-        while is_addsub_operator(self.tokens[self.current_token_index]) ||
-              is_whitespace(self.tokens[self.current_token_index]) {
-            let token = self.tokens[self.current_token_index];
+        self.depth = depth;
 
-            match token {
-                Token::Operator(Operator::Add) => {
-                    self.eat(token);
-                    node = Node::BinaryOperator(Box::new(BinaryOperator {
-                        left: node,
-                        token,
-                        right: self.term()
-                    }))
+        Ok(node)
+    }
+
+    fn expr(&mut self) -> Result<Node, Error> {
+        self.binary_expression(0)
+    }
+
+    // command := assignment | expr
+    //
+    // a single line of input outside of a program
+    fn command(&mut self) -> Result<Node, Error> {
+        self.skip_whitespace();
+
+        match (self.current_token().value, self.peek_token()) {
+            (Token::Id(_), Token::ASSIGN) => self.assignment(),
+            _ => self.expr()
+        }
+    }
+
+    // program := PROGRAM variable SEMI block DOT
+    fn program(&mut self) -> Result<Node, Error> {
+        self.skip_whitespace();
+        let token = self.eat(Token::PROGRAM)?;
+        let name = self.variable()?;
+
+        self.skip_whitespace();
+        self.eat(Token::SEMI)?;
+
+        let block = self.block()?;
+
+        self.skip_whitespace();
+        self.eat(Token::DOT)?;
+
+        Ok(Node::Program(Box::new(Program { token, name, block })))
+    }
+
+    // block := declarations compound_statement
+    fn block(&mut self) -> Result<Node, Error> {
+        let declarations = self.declarations()?;
+
+        Ok(Node::Block(Box::new(Block {
+            declarations,
+            compound_statement: self.compound_statement()?
+        })))
+    }
+
+    // declarations := (VAR (variable_declaration SEMI)+ | procedure_declaration)*
+    fn declarations(&mut self) -> Result<Vec<Node>, Error> {
+        let mut declarations = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+
+            match self.current_token().value {
+                Token::VAR => {},
+                Token::PROCEDURE | Token::FUNCTION => {
+                    declarations.push(self.procedure_declaration()?);
+                    continue
                 },
-                Token::Operator(Operator::Sub) => {
-                    self.eat(token);
-                    node = Node::BinaryOperator(Box::new(BinaryOperator {
-                        left: node,
-                        token,
-                        right: self.term()
-                    }))
+                _ => break
+            }
+
+            self.eat(Token::VAR)?;
+
+            loop {
+                declarations.extend(self.variable_declaration()?);
+
+                self.skip_whitespace();
+                self.eat(Token::SEMI)?;
+
+                self.skip_whitespace();
+                if let Token::Id(_) = self.current_token().value {
+                    continue
+                }
+
+                break
+            }
+        }
+
+        Ok(declarations)
+    }
+
+    // procedure_declaration := PROCEDURE variable (LPAREN formal_parameter_list RPAREN)? SEMI block SEMI
+    //                          | FUNCTION variable (LPAREN formal_parameter_list RPAREN)? COLON type_spec SEMI block SEMI
+    fn procedure_declaration(&mut self) -> Result<Node, Error> {
+        self.skip_whitespace();
+        let token = match self.current_token().value {
+            Token::FUNCTION => self.eat(Token::FUNCTION)?,
+            _ => self.eat(Token::PROCEDURE)?
+        };
+        self.enter(token.span)?;
+        let name = self.variable()?;
+        let mut params = Vec::new();
+
+        self.skip_whitespace();
+        if self.current_token().value == Token::LPAREN {
+            let lparen = self.eat(Token::LPAREN)?;
+            params = self.formal_parameter_list()?;
+
+            self.skip_whitespace();
+            self.eat(Token::RPAREN)
+                .map_err(|error| error.with_label(lparen.span, "unclosed `(` opened here"))?;
+        }
+
+        let mut return_type = None;
+
+        if token.value == Token::FUNCTION {
+            self.skip_whitespace();
+            self.eat(Token::COLON)?;
+            return_type = Some(self.type_spec()?);
+        }
+
+        self.skip_whitespace();
+        self.eat(Token::SEMI)?;
+
+        let block = self.block()?;
+        self.depth -= 1;
+
+        self.skip_whitespace();
+        self.eat(Token::SEMI)?;
+
+        Ok(Node::ProcedureDecl(Arc::new(ProcedureDecl { token, name, params, return_type, block })))
+    }
+
+    // formal_parameter_list := formal_parameters (SEMI formal_parameters)*
+    fn formal_parameter_list(&mut self) -> Result<Vec<Param>, Error> {
+        let mut params = self.formal_parameters()?;
+
+        loop {
+            self.skip_whitespace();
+
+            if self.current_token().value != Token::SEMI {
+                break
+            }
+
+            self.eat(Token::SEMI)?;
+            params.extend(self.formal_parameters()?);
+        }
+
+        Ok(params)
+    }
+
+    // formal_parameters := variable (COMMA variable)* COLON type_spec
+    fn formal_parameters(&mut self) -> Result<Vec<Param>, Error> {
+        let (names, type_spec) = self.typed_names()?;
+
+        Ok(names.into_iter().map(|name| Param { name, type_spec }).collect())
+    }
+
+    // variable_declaration := variable (COMMA variable)* COLON type_spec
+    fn variable_declaration(&mut self) -> Result<Vec<Node>, Error> {
+        let (names, type_spec) = self.typed_names()?;
+
+        Ok(names.into_iter()
+            .map(|name| Node::VarDecl(Box::new(VarDecl { name, type_spec })))
+            .collect())
+    }
+
+    // names sharing one type, as in variable declarations and formal parameters
+    fn typed_names(&mut self) -> Result<(Vec<Spanned<String>>, Spanned<Type>), Error> {
+        let mut names = vec![self.variable()?];
+
+        loop {
+            self.skip_whitespace();
+
+            if self.current_token().value != Token::COMMA {
+                break
+            }
+
+            self.eat(Token::COMMA)?;
+            names.push(self.variable()?);
+        }
+
+        self.skip_whitespace();
+        self.eat(Token::COLON)?;
+
+        Ok((names, self.type_spec()?))
+    }
+
+    // type_spec := INTEGER | REAL | BOOLEAN | STRING
+    fn type_spec(&mut self) -> Result<Spanned<Type>, Error> {
+        self.skip_whitespace();
+
+        let token = self.current_token();
+        let type_spec = match token.value {
+            Token::INTEGER => Type::Integer,
+            Token::REAL => Type::Real,
+            Token::BOOLEAN => Type::Boolean,
+            Token::STRING => Type::String,
+            _ => return Err(Error::syntax(
+                format!("expected INTEGER, REAL, BOOLEAN or STRING, found {}", token.value),
+                token.span
+            ))
+        };
+
+        self.next_token();
+
+        Ok(Spanned::new(type_spec, token.span))
+    }
+
+    // compound_statement := BEGIN statement_list END
+    fn compound_statement(&mut self) -> Result<Node, Error> {
+        self.skip_whitespace();
+        let begin = self.eat(Token::BEGIN)?;
+        let statements = self.statement_list()?;
+
+        self.skip_whitespace();
+        let end = self.eat(Token::END).map_err(|error| {
+            let error = error.with_label(begin.span, "unclosed BEGIN opened here");
+
+            match self.current_token().value {
+                Token::Id(_) | Token::BEGIN | Token::IF | Token::WHILE | Token::REPEAT | Token::FOR => {
+                    error.with_note("statements are separated by `;`")
                 },
-                Token::Whitespace => self.skip_whitespace(),
-                _ => panic!("Syntax Error: expected \"+\" or \"-\".")
+                Token::ELSE => error.with_note("the statement before ELSE must not end with `;`"),
+                _ => error
             }
+        })?;
+
+        Ok(Node::Compound(Spanned::new(statements, begin.span.to(end.span))))
+    }
+
+    // statement_list := statement (SEMI statement)*
+    fn statement_list(&mut self) -> Result<Vec<Node>, Error> {
+        let mut statements = vec![self.statement()?];
+
+        loop {
+            self.skip_whitespace();
+
+            if self.current_token().value != Token::SEMI {
+                break
+            }
+
+            self.eat(Token::SEMI)?;
+            statements.push(self.statement()?);
+        }
+
+        Ok(statements)
+    }
+
+    // statement := compound_statement | if_statement | while_statement | repeat_statement
+    //            | for_statement | assignment | procedure_call | empty
+    fn statement(&mut self) -> Result<Node, Error> {
+        self.skip_whitespace();
+        self.enter(self.current_token().span)?;
+
+        let statement = match (self.current_token().value, self.peek_token()) {
+            (Token::BEGIN, _) => self.compound_statement(),
+            (Token::IF, _) => self.if_statement(),
+            (Token::WHILE, _) => self.while_statement(),
+            (Token::REPEAT, _) => self.repeat_statement(),
+            (Token::FOR, _) => self.for_statement(),
+            (Token::Id(_), Token::ASSIGN) => self.assignment(),
+            (Token::Id(_), _) => self.procedure_call(),
+            _ => Ok(self.empty())
+        };
+        self.depth -= 1;
+
+        statement
+    }
+
+    // if_statement := IF expr THEN statement (ELSE statement)?
+    //
+    // an ELSE belongs to the innermost IF without one, so in
+    // `IF a THEN IF b THEN x ELSE y` it is part of `IF b`
+    fn if_statement(&mut self) -> Result<Node, Error> {
+        let token = self.eat(Token::IF)?;
+        let condition = self.expr()?;
+
+        self.skip_whitespace();
+        self.eat(Token::THEN).map_err(|error| error.with_label(token.span, "IF without THEN"))?;
+
+        let then_branch = self.statement()?;
+        let mut else_branch = None;
+
+        self.skip_whitespace();
+        if self.current_token().value == Token::ELSE {
+            self.eat(Token::ELSE)?;
+            else_branch = Some(self.statement()?);
         }
 
-        node
-    }
-
-    fn term(&mut self) -> Node {
-        let mut node = self.factor();
-
-        while is_muldiv_operator(self.tokens[self.current_token_index]) ||
-              is_whitespace(self.tokens[self.current_token_index]) {
-                let token = self.tokens[self.current_token_index];
-
-                match token {
-                    Token::Operator(Operator::Mul) => {
-                        self.eat(token);
-                        node = Node::BinaryOperator(Box::new(BinaryOperator {
-                            left: node,
-                            token,
-                            right: self.term()
-                        }))
-                    },
-                    Token::Operator(Operator::Div) => {
-                        self.eat(token);
-                        node = Node::BinaryOperator(Box::new(BinaryOperator {
-                            left: node,
-                            token,
-                            right: self.term()
-                        }))
-                    },
-                    Token::Whitespace => self.skip_whitespace(),
-                    _ => panic!("Syntax Error: expected \"*\" or \"/\".")
+        Ok(Node::If(Box::new(If { token, condition, then_branch, else_branch })))
+    }
+
+    // while_statement := WHILE expr DO statement
+    fn while_statement(&mut self) -> Result<Node, Error> {
+        let token = self.eat(Token::WHILE)?;
+        let condition = self.expr()?;
+
+        self.skip_whitespace();
+        self.eat(Token::DO).map_err(|error| error.with_label(token.span, "WHILE without DO"))?;
+
+        Ok(Node::While(Box::new(While { token, condition, body: self.statement()? })))
+    }
+
+    // repeat_statement := REPEAT statement_list UNTIL expr
+    fn repeat_statement(&mut self) -> Result<Node, Error> {
+        let token = self.eat(Token::REPEAT)?;
+        let body = self.statement_list()?;
+
+        self.skip_whitespace();
+        self.eat(Token::UNTIL).map_err(|error| error.with_label(token.span, "unclosed REPEAT opened here"))?;
+
+        Ok(Node::Repeat(Box::new(Repeat { token, body, condition: self.expr()? })))
+    }
+
+    // for_statement := FOR variable ASSIGN expr (TO | DOWNTO) expr DO statement
+    fn for_statement(&mut self) -> Result<Node, Error> {
+        let token = self.eat(Token::FOR)?;
+        let variable = self.variable()?;
+
+        self.skip_whitespace();
+        self.eat(Token::ASSIGN)?;
+
+        let start = self.expr()?;
+
+        self.skip_whitespace();
+        let direction = match self.current_token().value {
+            Token::DOWNTO => self.eat(Token::DOWNTO)?,
+            _ => self.eat(Token::TO)?
+        };
+        let end = self.expr()?;
+
+        self.skip_whitespace();
+        self.eat(Token::DO).map_err(|error| error.with_label(token.span, "FOR without DO"))?;
+
+        Ok(Node::For(Box::new(For { token, variable, start, direction, end, body: self.statement()? })))
+    }
+
+    // procedure_call := variable (LPAREN (expr (COMMA expr)*)? RPAREN)?
+    fn procedure_call(&mut self) -> Result<Node, Error> {
+        let name = self.variable()?;
+        let mut args = Vec::new();
+        let mut span = name.span;
+
+        self.skip_whitespace();
+        if self.current_token().value == Token::LPAREN {
+            let lparen = self.eat(Token::LPAREN)?;
+            self.enter(name.span)?;
+
+            self.skip_whitespace();
+            if self.current_token().value != Token::RPAREN {
+                args.push(self.expr()?);
+
+                loop {
+                    self.skip_whitespace();
+
+                    if self.current_token().value != Token::COMMA {
+                        break
+                    }
+
+                    self.eat(Token::COMMA)?;
+                    args.push(self.expr()?);
                 }
-              }
-        
-        node
+            }
+
+            self.skip_whitespace();
+            let rparen = self.eat(Token::RPAREN)
+                .map_err(|error| error.with_label(lparen.span, "unclosed `(` opened here"))?;
+            span = span.to(rparen.span);
+            self.depth -= 1;
+        }
+
+        Ok(Node::ProcedureCall(Box::new(ProcedureCall { name, args, span })))
+    }
+
+    // empty statement at the start of the current token
+    fn empty(&self) -> Node {
+        let span = self.current_token().span;
+
+        Node::NoOp(Span::new(span.start, span.start, span.line, span.column))
     }
+
+    // assignment := variable ASSIGN expr
+    fn assignment(&mut self) -> Result<Node, Error> {
+        let name = self.variable()?;
+
+        self.skip_whitespace();
+        let token = self.eat(Token::ASSIGN)?;
+
+        Ok(Node::Assign(Box::new(Assign {
+            name,
+            token,
+            expr: self.expr()?
+        })))
+    }
+}
+
+pub fn parse(tokens: Vec<Spanned<Token>>) -> Result<AST, Error> {
+    parse_with_max_depth(tokens, DEFAULT_MAX_NESTING_DEPTH)
 }
 
-pub fn parse(tokens: Vec<Token>) -> AST {
-    let parser = &mut Parser {
-        current_token_index: 0,
-        tokens
+// like `parse`, but reports nesting deeper than `max_depth` levels as an error
+pub fn parse_with_max_depth(tokens: Vec<Spanned<Token>>, max_depth: usize) -> Result<AST, Error> {
+    let parser = &mut Parser::new(tokens, max_depth);
+
+    parser.skip_whitespace();
+    let root = match parser.current_token().value {
+        Token::PROGRAM => parser.program()?,
+        _ => parser.command()?
     };
 
-    AST {
-        root: parser.expr()
-    }
+    parser.skip_whitespace();
+    parser.eat(Token::EOF).map_err(|error| match parser.current_token().value {
+        Token::RPAREN => error.with_note("this `)` has no matching `(`"),
+        _ => error
+    })?;
+
+    Ok(AST {
+        root
+    })
 }
 
-#[cfg(test)]
 // TODO:
 // - put these in their own test_file
 
-fn setup_tokens() -> Vec<Token> {
-    vec![
+// gives every token a one character wide span, as if the tokens were lexed
+// from a source text without any multi-character tokens
+#[cfg(test)]
+fn spanned(tokens: Vec<Token>) -> Vec<Spanned<Token>> {
+    tokens.into_iter()
+        .enumerate()
+        .map(|(index, token)| Spanned::new(token, Span::new(index, index + 1, 1, index + 1)))
+        .collect()
+}
+
+#[cfg(test)]
+fn setup_tokens() -> Vec<Spanned<Token>> {
+    spanned(vec![
         Token::Number(Number::Integer(3)),
         Token::Whitespace,
         Token::Operator(Operator::Mul),
@@ -146,16 +668,13 @@ fn setup_tokens() -> Vec<Token> {
         Token::Operator(Operator::Div),
         Token::Whitespace,
         Token::Number(Number::Integer(1))
-    ]
+    ])
 }
 
 #[test]
 fn next_token_increments_current_token_index() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 0,
-        tokens
-    };
+    let parser = &mut Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH);
     assert_eq!(parser.current_token_index, 0);
 
     parser.next_token();
@@ -165,10 +684,7 @@ fn next_token_increments_current_token_index() {
 #[test]
 fn next_token_increment_is_clamped_to_length_of_token_vector() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 0,
-        tokens
-    };
+    let parser = &mut Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH);
     assert_eq!(parser.current_token_index, 0);
 
     for _ in 0..12 {
@@ -179,51 +695,43 @@ fn next_token_increment_is_clamped_to_length_of_token_vector() {
 }
 
 #[test]
-#[should_panic]
 fn eat_returns_error_if_the_passed_token_is_not_the_current_token() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 0,
-        tokens
-    };
+    let parser = &mut Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH);
 
-    parser.eat(Token::Whitespace);
+    let error = parser.eat(Token::Whitespace).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Syntax);
+    assert_eq!(error.span, Span::new(0, 1, 1, 1));
 }
 
 #[test]
 fn eat_returns_current_token_if_passed_token_matches() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 0,
-        tokens
-    };
+    let parser = &mut Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH);
 
-    assert_eq!(parser.eat(Token::Number(Number::Integer(3))), Token::Number(Number::Integer(3)));
+    assert_eq!(
+        parser.eat(Token::Number(Number::Integer(3))),
+        Ok(Spanned::new(Token::Number(Number::Integer(3)), Span::new(0, 1, 1, 1)))
+    );
 }
 
 #[test]
 fn eat_advances_current_token_index() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 0,
-        tokens
-    };
+    let parser = &mut Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH);
     assert_eq!(parser.current_token_index, 0);
 
-    parser.eat(Token::Number(Number::Integer(3)));
+    parser.eat(Token::Number(Number::Integer(3))).unwrap();
     assert_eq!(parser.current_token_index, 1);
 }
 
 #[test]
 fn factor_skips_whitespace_tokens() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 3,
-        tokens
-    };
+    let parser = &mut Parser { current_token_index: 3, ..Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH) };
     assert_eq!(parser.current_token_index, 3);
 
-    parser.factor();
+    parser.factor().unwrap();
 
     assert_eq!(parser.current_token_index, 5);
 }
@@ -231,34 +739,411 @@ fn factor_skips_whitespace_tokens() {
 #[test]
 fn factor_returns_number_token_as_node() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 0,
-        tokens
-    };
+    let parser = &mut Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH);
     let node = parser.factor();
-    assert_eq!(node, Node::Token(Token::Number(Number::Integer(3))));
+    assert_eq!(node, Ok(Node::Token(Spanned::new(Token::Number(Number::Integer(3)), Span::new(0, 1, 1, 1)))));
 }
 
 #[test]
-#[should_panic]
 fn factor_throws_syntax_error_on_operator() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 2,
-        tokens
-    };
-    parser.factor();
+    let parser = &mut Parser { current_token_index: 2, ..Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH) };
+    let error = parser.factor().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Syntax);
 }
 
 #[test]
 fn factor_consumes_the_token() {
     let tokens = setup_tokens();
-    let parser = &mut Parser {
-        current_token_index: 0,
-        tokens
-    };
+    let parser = &mut Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH);
     assert_eq!(parser.current_token_index, 0);
 
-    parser.factor();
+    parser.factor().unwrap();
     assert_eq!(parser.current_token_index, 1);
-}
\ No newline at end of file
+}
+
+#[test]
+fn parse_returns_syntax_error_on_unclosed_paren() {
+    let tokens = spanned(vec![
+        Token::LPAREN,
+        Token::Number(Number::Integer(1)),
+        Token::EOF
+    ]);
+    let error = parse(tokens).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Syntax);
+    assert_eq!(error.message, "expected RPAREN, found EOF");
+    assert_eq!(error.labels, [Label {
+        span: Span::new(0, 1, 1, 1),
+        message: String::from("unclosed `(` opened here")
+    }]);
+}
+
+#[test]
+fn parse_returns_syntax_error_on_unopened_paren() {
+    let tokens = spanned(vec![
+        Token::Number(Number::Integer(1)),
+        Token::RPAREN,
+        Token::EOF
+    ]);
+    let error = parse(tokens).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::Syntax);
+    assert_eq!(error.message, "expected EOF, found RPAREN");
+    assert_eq!(error.span, Span::new(1, 2, 1, 2));
+    assert_eq!(error.notes, ["this `)` has no matching `(`"]);
+}
+
+#[test]
+fn parse_keeps_operator_spans_in_the_ast() {
+    let tokens = spanned(vec![
+        Token::Number(Number::Integer(1)),
+        Token::Operator(Operator::Add),
+        Token::Number(Number::Integer(2)),
+        Token::EOF
+    ]);
+    let ast = parse(tokens).unwrap();
+
+    match ast.root {
+        Node::BinaryOperator(node) => assert_eq!(node.token.span, Span::new(1, 2, 1, 2)),
+        _ => panic!("expected a BinaryOperator node")
+    }
+}
+
+#[test]
+fn factor_returns_stacked_unary_operators() {
+    let tokens = spanned(vec![
+        Token::Operator(Operator::Sub),
+        Token::Operator(Operator::Sub),
+        Token::Number(Number::Integer(3)),
+        Token::EOF
+    ]);
+    let parser = &mut Parser::new(tokens, DEFAULT_MAX_NESTING_DEPTH);
+
+    assert_eq!(parser.factor(), Ok(Node::UnaryOperator(Box::new(UnaryOperator {
+        token: Spanned::new(Token::Operator(Operator::Sub), Span::new(0, 1, 1, 1)),
+        expr: Node::UnaryOperator(Box::new(UnaryOperator {
+            token: Spanned::new(Token::Operator(Operator::Sub), Span::new(1, 2, 1, 2)),
+            expr: Node::Token(Spanned::new(Token::Number(Number::Integer(3)), Span::new(2, 3, 1, 3)))
+        }))
+    }))));
+}
+
+#[test]
+fn parse_accepts_unary_operators_after_binary_operators() {
+    // 2 * -(1 + 1)
+    let tokens = spanned(vec![
+        Token::Number(Number::Integer(2)),
+        Token::Operator(Operator::Mul),
+        Token::Operator(Operator::Sub),
+        Token::LPAREN,
+        Token::Number(Number::Integer(1)),
+        Token::Operator(Operator::Add),
+        Token::Number(Number::Integer(1)),
+        Token::RPAREN,
+        Token::EOF
+    ]);
+    let ast = parse(tokens).unwrap();
+
+    match ast.root {
+        Node::BinaryOperator(node) => match node.right {
+            Node::UnaryOperator(_) => (),
+            _ => panic!("expected a UnaryOperator node on the right")
+        },
+        _ => panic!("expected a BinaryOperator node")
+    }
+}
+
+
+// renders the tree with explicit parentheses, e.g. `((8 / 4) / 2)`
+#[cfg(test)]
+fn parenthesize(node: &Node) -> String {
+    match node {
+        Node::Token(token) => match token.value {
+            Token::Number(Number::Integer(value)) => value.to_string(),
+            Token::Number(Number::Real(value)) => value.to_string(),
+            Token::Number(Number::MinIntegerMagnitude) => (-i64::from(i32::MIN)).to_string(),
+            Token::String(ref value) => format!("'{}'", value.replace('\'', "''")),
+            _ => token.value.to_string()
+        },
+        Node::BinaryOperator(node) => format!(
+            "({} {} {})",
+            parenthesize(&node.left),
+            node.token.value,
+            parenthesize(&node.right)
+        ),
+        Node::UnaryOperator(node) => format!("({} {})", node.token.value, parenthesize(&node.expr)),
+        Node::Assign(node) => format!("({} := {})", node.name.value, parenthesize(&node.expr)),
+        Node::Var(name) => name.value.clone(),
+        Node::Program(node) => format!("PROGRAM {}; {}.", node.name.value, parenthesize(&node.block)),
+        Node::Compound(node) => format!(
+            "BEGIN {} END",
+            node.value.iter().map(parenthesize).collect::<Vec<String>>().join("; ")
+        ),
+        Node::NoOp(_) => "NoOp".to_string(),
+        Node::Block(node) => node.declarations.iter()
+            .map(|declaration| format!("{}; ", parenthesize(declaration)))
+            .chain(std::iter::once(parenthesize(&node.compound_statement)))
+            .collect(),
+        Node::VarDecl(node) => format!("VAR {}: {}", node.name.value, node.type_spec.value),
+        Node::If(node) => match &node.else_branch {
+            Some(else_branch) => format!(
+                "IF {} THEN {} ELSE {}",
+                parenthesize(&node.condition),
+                parenthesize(&node.then_branch),
+                parenthesize(else_branch)
+            ),
+            None => format!("IF {} THEN {}", parenthesize(&node.condition), parenthesize(&node.then_branch))
+        },
+        Node::While(node) => format!("WHILE {} DO {}", parenthesize(&node.condition), parenthesize(&node.body)),
+        Node::Repeat(node) => format!(
+            "REPEAT {} UNTIL {}",
+            node.body.iter().map(parenthesize).collect::<Vec<String>>().join("; "),
+            parenthesize(&node.condition)
+        ),
+        Node::For(node) => format!(
+            "FOR {} := {} {} {} DO {}",
+            node.variable.value,
+            parenthesize(&node.start),
+            node.direction.value,
+            parenthesize(&node.end),
+            parenthesize(&node.body)
+        ),
+        Node::ProcedureCall(node) => format!(
+            "{}({})",
+            node.name.value,
+            node.args.iter().map(parenthesize).collect::<Vec<String>>().join(", ")
+        ),
+        Node::ProcedureDecl(node) => format!(
+            "{} {}({}){}; {}",
+            node.token.value,
+            node.name.value,
+            node.params.iter()
+                .map(|param| format!("{}: {}", param.name.value, param.type_spec.value))
+                .collect::<Vec<String>>()
+                .join(", "),
+            node.return_type.map(|type_spec| format!(": {}", type_spec.value)).unwrap_or_default(),
+            parenthesize(&node.block)
+        )
+    }
+}
+
+#[cfg(test)]
+fn parse_text(text: &str) -> String {
+    parenthesize(&parse(crate::lexer::lex(text).unwrap()).unwrap().root)
+}
+
+#[test]
+fn parse_folds_left_associative_operators_to_the_left() {
+    assert_eq!(parse_text("8 / 4 / 2"), "((8 Operator(Div) 4) Operator(Div) 2)");
+    assert_eq!(parse_text("1 - 2 - 3"), "((1 Operator(Sub) 2) Operator(Sub) 3)");
+}
+
+#[test]
+fn parse_respects_operator_precedence() {
+    assert_eq!(parse_text("1 + 2 * 3 - 4"), "((1 Operator(Add) (2 Operator(Mul) 3)) Operator(Sub) 4)");
+    assert_eq!(parse_text("-2 * 3"), "((Operator(Sub) 2) Operator(Mul) 3)");
+}
+
+#[test]
+fn parse_skips_runs_of_whitespace() {
+    assert_eq!(parse_text("1  +   2"), "(1 Operator(Add) 2)");
+}
+
+#[test]
+fn parse_folds_right_associative_operators_to_the_right() {
+    assert_eq!(parse_text("2 ** 3 ^ 2"), "(2 Operator(Pow) (3 Operator(Pow) 2))");
+}
+
+#[test]
+fn parse_binds_exponents_tighter_than_multiplication_and_prefix_operators() {
+    assert_eq!(parse_text("2 * 3 ** 2 % 5"), "((2 Operator(Mul) (3 Operator(Pow) 2)) Operator(Mod) 5)");
+    assert_eq!(parse_text("-2 ** 2"), "(Operator(Sub) (2 Operator(Pow) 2))");
+    assert_eq!(parse_text("2 ** -1"), "(2 Operator(Pow) (Operator(Sub) 1))");
+}
+
+#[cfg(not(feature = "bignum"))]
+#[test]
+fn parse_negates_the_digits_of_the_smallest_integer_like_any_other_number() {
+    assert_eq!(parse_text("-2147483648 ** 2"), "(Operator(Sub) (2147483648 Operator(Pow) 2))");
+    assert_eq!(parse_text("-2147483648 * 2"), "((Operator(Sub) 2147483648) Operator(Mul) 2)");
+}
+
+#[test]
+fn parse_returns_assignments_of_expressions_over_variables() {
+    assert_eq!(parse_text("x := y * (2 + x)"), "(x := (y Operator(Mul) (2 Operator(Add) x)))");
+}
+
+#[test]
+fn parse_treats_a_lone_variable_as_an_expression() {
+    assert_eq!(parse_text(" x - 1"), "(x Operator(Sub) 1)");
+}
+
+#[test]
+fn parse_returns_syntax_error_on_assignment_to_an_expression() {
+    let error = parse(crate::lexer::lex("x + 1 := 2").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected EOF, found ASSIGN");
+}
+
+#[test]
+fn parse_returns_programs_of_nested_compound_statements() {
+    assert_eq!(
+        parse_text("PROGRAM p; BEGIN x := 1; BEGIN y := x END; END."),
+        "PROGRAM p; BEGIN (x := 1); BEGIN (y := x) END; NoOp END."
+    );
+}
+
+#[test]
+fn parse_accepts_keywords_regardless_of_case() {
+    assert_eq!(parse_text("program p;\nbegin\nend."), "PROGRAM p; BEGIN NoOp END.");
+}
+
+#[test]
+fn parse_returns_syntax_error_on_missing_semicolon() {
+    let error = parse(crate::lexer::lex("PROGRAM p; BEGIN x := 1 y := 2 END.").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected END, found Id(\"y\")");
+    assert_eq!(error.labels[0].message, "unclosed BEGIN opened here");
+    assert_eq!(error.notes, ["statements are separated by `;`"]);
+}
+
+#[test]
+fn parse_returns_syntax_error_on_missing_final_dot() {
+    let error = parse(crate::lexer::lex("PROGRAM p; BEGIN END").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected DOT, found EOF");
+}
+
+#[test]
+fn parse_returns_one_declaration_per_variable() {
+    assert_eq!(
+        parse_text("PROGRAM p; VAR a, b : INTEGER; y : REAL; VAR z : REAL; BEGIN END."),
+        "PROGRAM p; VAR a: INTEGER; VAR b: INTEGER; VAR y: REAL; VAR z: REAL; BEGIN NoOp END."
+    );
+}
+
+#[test]
+fn parse_returns_syntax_error_on_unknown_types() {
+    let error = parse(crate::lexer::lex("PROGRAM p; VAR a : x; BEGIN END.").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected INTEGER, REAL, BOOLEAN or STRING, found Id(\"x\")");
+}
+
+#[test]
+fn parse_returns_nested_procedure_declarations_with_parameters() {
+    assert_eq!(
+        parse_text("PROGRAM p; VAR x : REAL; PROCEDURE a(i, j : INTEGER; y : REAL); PROCEDURE b; BEGIN END; BEGIN END; BEGIN END."),
+        "PROGRAM p; VAR x: REAL; PROCEDURE a(i: INTEGER, j: INTEGER, y: REAL); PROCEDURE b(); BEGIN NoOp END; BEGIN NoOp END; BEGIN NoOp END."
+    );
+}
+
+#[test]
+fn parse_returns_syntax_error_on_missing_semicolon_after_procedure() {
+    let error = parse(crate::lexer::lex("PROGRAM p; PROCEDURE a; BEGIN END BEGIN END.").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected SEMI, found BEGIN");
+}
+
+#[test]
+fn parse_returns_procedure_calls_with_and_without_arguments() {
+    assert_eq!(
+        parse_text("PROGRAM p; BEGIN a(1 + 2, x); b; c() END."),
+        "PROGRAM p; BEGIN a((1 Operator(Add) 2), x); b(); c() END."
+    );
+}
+
+#[test]
+fn parse_returns_syntax_error_on_unclosed_argument_list() {
+    let error = parse(crate::lexer::lex("PROGRAM p; BEGIN a(1, 2 END.").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected RPAREN, found END");
+    assert_eq!(error.labels[0].message, "unclosed `(` opened here");
+}
+
+#[test]
+fn parse_returns_function_declarations_and_calls_in_expressions() {
+    assert_eq!(
+        parse_text("PROGRAM p; VAR x : REAL; FUNCTION f(i : INTEGER) : REAL; BEGIN f := i / 2 END; BEGIN x := 1 + f (3) * y END."),
+        "PROGRAM p; VAR x: REAL; FUNCTION f(i: INTEGER): REAL; BEGIN (f := (i Operator(Div) 2)) END; \
+        BEGIN (x := (1 Operator(Add) (f(3) Operator(Mul) y))) END."
+    );
+}
+
+#[test]
+fn parse_returns_syntax_error_on_function_without_return_type() {
+    let error = parse(crate::lexer::lex("PROGRAM p; FUNCTION f(i : INTEGER); BEGIN END; BEGIN END.").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected COLON, found SEMI");
+}
+
+#[test]
+fn parse_binds_logical_operators_tighter_than_comparisons() {
+    assert_eq!(
+        parse_text("a < b + 1 = TRUE"),
+        "((a Operator(Less) (b Operator(Add) 1)) Operator(Equal) TRUE)"
+    );
+    assert_eq!(
+        parse_text("NOT a OR b AND c <> d"),
+        "(((Operator(Not) a) Operator(Or) (b Operator(And) c)) Operator(NotEqual) d)"
+    );
+}
+
+#[test]
+fn parse_returns_string_literals_and_declarations() {
+    assert_eq!(
+        parse_text("PROGRAM p; VAR s : STRING; BEGIN s := 'it''s' + copy(s, 1, 2) END."),
+        "PROGRAM p; VAR s: STRING; BEGIN (s := ('it''s' Operator(Add) copy(s, 1, 2))) END."
+    );
+}
+
+#[test]
+fn parse_reports_nesting_deeper_than_the_maximum_depth() {
+    let parse_text = |text: &str, max_depth: usize| parse_with_max_depth(crate::lexer::lex(text).unwrap(), max_depth);
+
+    assert!(parse_text("-(1 + 2)", 3).is_ok());
+    assert!(parse_text("1 + 2 + 3 + 4", 3).is_ok());
+    assert!(parse_text("PROGRAM p; BEGIN BEGIN IF a THEN f(x) END END.", 4).is_ok());
+
+    let error = parse_text("1 + 2 + 3 + 4", 2).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Syntax);
+    assert_eq!(error.message, "maximum nesting depth of 2 exceeded");
+    assert_eq!(error.span, Span::new(10, 11, 1, 11));
+
+    assert_eq!(parse_text("-(-(1))", 3).unwrap_err().span, Span::new(3, 4, 1, 4));
+    assert_eq!(parse_text("f(g(h(1)))", 2).unwrap_err().span, Span::new(4, 5, 1, 5));
+    assert_eq!(parse_text("PROGRAM p; BEGIN BEGIN IF a THEN x := 1 END END.", 2).unwrap_err().span, Span::new(33, 34, 1, 34));
+}
+
+#[test]
+fn parse_attaches_else_to_the_innermost_if() {
+    assert_eq!(
+        parse_text("PROGRAM p; BEGIN IF a THEN IF b THEN x := 1 ELSE x := 2; IF c THEN BEGIN END ELSE y := 3 END."),
+        "PROGRAM p; BEGIN IF a THEN IF b THEN (x := 1) ELSE (x := 2); IF c THEN BEGIN NoOp END ELSE (y := 3) END."
+    );
+}
+
+#[test]
+fn parse_explains_semicolons_before_else() {
+    let error = parse(crate::lexer::lex("PROGRAM p; BEGIN IF a THEN x := 1; ELSE x := 2 END.").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected END, found ELSE");
+    assert_eq!(error.notes, ["the statement before ELSE must not end with `;`"]);
+}
+
+#[test]
+fn parse_returns_while_repeat_and_for_loops() {
+    assert_eq!(
+        parse_text("PROGRAM p; BEGIN WHILE i < 3 DO i := i + 1; REPEAT i := i - 1; UNTIL i = 0; FOR i := 10 DOWNTO 1 DO BEGIN END END."),
+        "PROGRAM p; BEGIN WHILE (i Operator(Less) 3) DO (i := (i Operator(Add) 1)); \
+        REPEAT (i := (i Operator(Sub) 1)); NoOp UNTIL (i Operator(Equal) 0); FOR i := 10 DOWNTO 1 DO BEGIN NoOp END END."
+    );
+}
+
+#[test]
+fn parse_returns_syntax_error_on_repeat_without_until() {
+    let error = parse(crate::lexer::lex("PROGRAM p; BEGIN REPEAT i := 1 END.").unwrap()).unwrap_err();
+
+    assert_eq!(error.message, "expected UNTIL, found END");
+    assert_eq!(error.labels[0].message, "unclosed REPEAT opened here");
+}
@@ -0,0 +1,465 @@
+use crate::types::*;
+use crate::ast::*;
+use crate::error::*;
+use crate::builtins::signature;
+
+mod symbols;
+mod type_checker;
+
+pub use self::symbols::{ScopedSymbolTable, Symbol};
+pub use self::type_checker::TypeChecker;
+
+// checks a program before it runs, reporting every undeclared identifier and
+// duplicate declaration instead of stopping at the first one
+#[derive(Debug)]
+pub struct SemanticAnalyzer {
+    // innermost scope last, the built-in types are always at the bottom
+    scopes: Vec<ScopedSymbolTable>,
+    // scopes already left, to explain references to names declared in them
+    closed_scopes: Vec<ScopedSymbolTable>,
+    // variables of the FOR loops around the statement being visited
+    loop_variables: Vec<Spanned<String>>,
+    // errors and warnings, in the order they were found
+    pub errors: Vec<Error>
+}
+
+impl Default for SemanticAnalyzer {
+    fn default() -> SemanticAnalyzer {
+        SemanticAnalyzer {
+            scopes: vec![ScopedSymbolTable::builtins()],
+            closed_scopes: Vec::new(),
+            loop_variables: Vec::new(),
+            errors: Vec::new()
+        }
+    }
+}
+
+impl SemanticAnalyzer {
+    pub fn analyze(&mut self, ast: &AST) {
+        self.visit(&ast.root);
+    }
+
+    fn enter_scope(&mut self, scope_name: &str) {
+        let scope_level = self.scopes.len();
+
+        self.scopes.push(ScopedSymbolTable::new(scope_name, scope_level));
+    }
+
+    fn leave_scope(&mut self) {
+        if let Some(scope) = self.scopes.pop() {
+            self.closed_scopes.push(scope);
+        }
+    }
+
+    fn current_scope(&self) -> &ScopedSymbolTable {
+        self.scopes.last().expect("the builtins scope is never left")
+    }
+
+    // looks `name` up in the innermost scope declaring it, returning that scope too
+    fn resolve(&self, name: &str) -> Option<(&ScopedSymbolTable, &Symbol)> {
+        self.scopes.iter().rev().find_map(|scope| scope.lookup(name).map(|symbol| (scope, symbol)))
+    }
+
+    // like `resolve`, but skips the result variable of the function `name`
+    // within its own body, so the function can call itself
+    fn resolve_call(&self, name: &str) -> Option<(&ScopedSymbolTable, &Symbol)> {
+        self.scopes.iter().rev()
+            .filter_map(|scope| scope.lookup(name).map(|symbol| (scope, symbol)))
+            .find(|(scope, symbol)| !(matches!(symbol, Symbol::Var { .. }) && scope.scope_name == name))
+    }
+
+    fn declare(&mut self, name: &Spanned<String>, symbol: Symbol) {
+        if let Some(previous) = self.current_scope().lookup(&name.value) {
+            let mut error = Error::semantic(format!("duplicate declaration of \"{}\"", name.value), name.span);
+
+            if let Some(span) = previous.span() {
+                error = error.with_label(span, "first declared here");
+            }
+
+            self.errors.push(error);
+            return
+        }
+
+        // declaring a name of an enclosing scope again is allowed, but hides the outer one
+        if let Some((scope, Some(span))) = self.resolve(&name.value).map(|(scope, symbol)| (scope, symbol.span())) {
+            let warning = Error::semantic(
+                format!("\"{}\" shadows a declaration of scope \"{}\" (level {})", name.value, scope.scope_name, scope.scope_level),
+                name.span
+            ).with_label(span, "shadowed declaration").into_warning();
+
+            self.errors.push(warning);
+        }
+
+        let scope = self.scopes.last_mut().expect("the builtins scope is never left");
+        scope.insert(name.value.clone(), symbol);
+    }
+
+    fn declare_variable(&mut self, name: &Spanned<String>, type_spec: &Spanned<Type>) {
+        if self.resolve(&type_spec.value.to_string()).is_none() {
+            self.errors.push(Error::semantic(format!("unknown type \"{}\"", type_spec.value), type_spec.span));
+        }
+
+        let symbol = Symbol::Var { name: name.value.clone(), type_spec: type_spec.value, span: name.span };
+        self.declare(name, symbol);
+    }
+
+    fn check_variable(&mut self, name: &Spanned<String>) {
+        let error = match self.resolve(&name.value) {
+            Some((_, Symbol::Var { .. })) => return,
+            Some((_, symbol)) => {
+                let error = Error::semantic(format!("\"{}\" is not a variable", name.value), name.span);
+
+                match symbol.span() {
+                    Some(span) => error.with_label(span, format!("declared as {} here", symbol.description())),
+                    None => error
+                }
+            },
+            None => {
+                let closed = self.closed_scopes.iter()
+                    .find_map(|scope| scope.lookup(&name.value).and_then(Symbol::span).map(|span| (scope, span)));
+
+                let error = match closed {
+                    Some((scope, span)) => Error::semantic(format!("variable \"{}\" is out of scope", name.value), name.span)
+                        .with_label(span, format!("declared in scope \"{}\" (level {})", scope.scope_name, scope.scope_level)),
+                    None => Error::semantic(format!("variable \"{}\" is not declared", name.value), name.span)
+                };
+                let scope = self.current_scope();
+
+                error.with_note(format!("referenced in scope \"{}\" (level {})", scope.scope_name, scope.scope_level))
+            }
+        };
+
+        self.errors.push(error);
+    }
+
+    // FOR loops assign their variable on every iteration, nothing else may
+    fn check_loop_variable(&mut self, name: &Spanned<String>) {
+        if let Some(variable) = self.loop_variables.iter().find(|variable| variable.value == name.value) {
+            let error = Error::semantic(format!("cannot assign to loop variable \"{}\"", name.value), name.span)
+                .with_label(variable.span, "loop variable of this FOR loop");
+
+            self.errors.push(error);
+        }
+    }
+
+    // `in_expression` for calls whose value is used, which only functions have
+    fn check_call(&mut self, node: &ProcedureCall, in_expression: bool) {
+        let error = match self.resolve_call(&node.name.value) {
+            Some((_, symbol @ Symbol::Procedure { params, span, .. })) if params.len() != node.args.len() => {
+                Error::semantic(
+                    format!(
+                        "{} \"{}\" takes {} but {} given",
+                        symbol.description(),
+                        node.name.value,
+                        count(params.len(), "argument"),
+                        count(node.args.len(), "was")
+                    ),
+                    node.span
+                ).with_label(*span, "declared here")
+            },
+            Some((_, Symbol::Procedure { return_type: None, span, .. })) if in_expression => {
+                Error::semantic(format!("procedure \"{}\" does not return a value", node.name.value), node.span)
+                    .with_label(*span, "declared as procedure here")
+                    .with_note("declare it as a FUNCTION with a return type to use it in expressions")
+            },
+            Some((_, Symbol::BuiltinFunction(builtin))) if builtin.params.len() != node.args.len() => {
+                Error::semantic(
+                    format!(
+                        "built-in function \"{}\" takes {} but {} given",
+                        node.name.value,
+                        count(builtin.params.len(), "argument"),
+                        count(node.args.len(), "was")
+                    ),
+                    node.span
+                ).with_note(format!("its parameters are ({})", signature(builtin.params)))
+            },
+            Some((_, Symbol::Procedure { .. })) | Some((_, Symbol::BuiltinFunction(_))) => return,
+            Some((_, symbol)) => {
+                let expected = if in_expression { "function" } else { "procedure" };
+                let error = Error::semantic(format!("\"{}\" is not a {}", node.name.value, expected), node.name.span);
+
+                match symbol.span() {
+                    Some(span) => error.with_label(span, format!("declared as {} here", symbol.description())),
+                    None => error
+                }
+            },
+            None => {
+                let scope = self.current_scope();
+
+                let expected = if in_expression { "function" } else { "procedure" };
+
+                Error::semantic(format!("{} \"{}\" is not declared", expected, node.name.value), node.name.span)
+                    .with_note(format!("referenced in scope \"{}\" (level {})", scope.scope_name, scope.scope_level))
+            }
+        };
+
+        self.errors.push(error);
+    }
+
+    fn visit(&mut self, node: &Node) {
+        match node {
+            Node::ProcedureCall(node) => self.visit_call(node, false),
+            Node::Program(node) => {
+                self.enter_scope(&node.name.value);
+                self.visit(&node.block);
+                self.leave_scope();
+            },
+            Node::Block(node) => {
+                for declaration in &node.declarations {
+                    self.visit(declaration);
+                }
+
+                self.visit(&node.compound_statement);
+            },
+            Node::VarDecl(node) => self.declare_variable(&node.name, &node.type_spec),
+            Node::ProcedureDecl(node) => {
+                let params = node.params.iter()
+                    .map(|param| (param.name.value.clone(), param.type_spec.value))
+                    .collect();
+
+                // declared before its body, so the procedure can call itself
+                let return_type = node.return_type.map(|type_spec| type_spec.value);
+
+                self.declare(&node.name, Symbol::Procedure { name: node.name.value.clone(), params, return_type, span: node.name.span });
+                self.enter_scope(&node.name.value);
+
+                // functions return the value assigned to their name, which is
+                // a variable within their own scope rather than a redeclaration
+                if let Some(type_spec) = return_type {
+                    let symbol = Symbol::Var { name: node.name.value.clone(), type_spec, span: node.name.span };
+                    self.scopes.last_mut().expect("just entered").insert(node.name.value.clone(), symbol);
+                }
+
+                for param in &node.params {
+                    self.declare_variable(&param.name, &param.type_spec);
+                }
+
+                self.visit(&node.block);
+                self.leave_scope();
+            },
+            Node::Compound(node) => {
+                for statement in &node.value {
+                    self.visit(statement);
+                }
+            },
+            Node::If(node) => {
+                self.visit_expression(&node.condition);
+                self.visit(&node.then_branch);
+
+                if let Some(else_branch) = &node.else_branch {
+                    self.visit(else_branch);
+                }
+            },
+            Node::While(node) => {
+                self.visit_expression(&node.condition);
+                self.visit(&node.body);
+            },
+            Node::Repeat(node) => {
+                for statement in &node.body {
+                    self.visit(statement);
+                }
+
+                self.visit_expression(&node.condition);
+            },
+            Node::For(node) => {
+                self.check_variable(&node.variable);
+                self.check_loop_variable(&node.variable);
+                self.visit_expression(&node.start);
+                self.visit_expression(&node.end);
+
+                self.loop_variables.push(node.variable.clone());
+                self.visit(&node.body);
+                self.loop_variables.pop();
+            },
+            Node::Assign(node) => {
+                self.check_variable(&node.name);
+                self.check_loop_variable(&node.name);
+                self.visit_expression(&node.expr);
+            },
+            node => self.visit_expression(node)
+        }
+    }
+
+    // like `visit`, but calls in `node` have to return a value
+    fn visit_expression(&mut self, node: &Node) {
+        match node {
+            Node::ProcedureCall(node) => self.visit_call(node, true),
+            Node::Var(name) => self.check_variable(name),
+            Node::BinaryOperator(node) => {
+                self.visit_expression(&node.left);
+                self.visit_expression(&node.right);
+            },
+            Node::UnaryOperator(node) => self.visit_expression(&node.expr),
+            Node::Token(_) | Node::NoOp(_) => {},
+            Node::Program(_) | Node::Block(_) | Node::VarDecl(_) | Node::ProcedureDecl(_) | Node::If(_) |
+            Node::While(_) | Node::Repeat(_) | Node::For(_) | Node::Assign(_) | Node::Compound(_) => self.visit(node)
+        }
+    }
+
+    fn visit_call(&mut self, node: &ProcedureCall, in_expression: bool) {
+        self.check_call(node, in_expression);
+
+        for arg in &node.args {
+            self.visit_expression(arg);
+        }
+    }
+}
+
+// e.g. "1 argument" and "2 arguments", or "1 was" and "2 were"
+fn count(count: usize, word: &str) -> String {
+    match (count, word) {
+        (1, word) => format!("1 {}", word),
+        (count, "was") => format!("{} were", count),
+        (count, word) => format!("{} {}s", count, word)
+    }
+}
+
+// the warnings about `ast`, or every error and warning if there are errors
+pub fn analyze(ast: &AST) -> Result<Vec<Error>, Vec<Error>> {
+    let analyzer = &mut SemanticAnalyzer::default();
+
+    analyzer.analyze(ast);
+
+    if analyzer.errors.iter().all(Error::is_warning) {
+        Ok(analyzer.errors.clone())
+    } else {
+        Err(analyzer.errors.clone())
+    }
+}
+
+// every type error in `ast`, storing the inferred types on the AST as it goes;
+// expects `ast` to have passed `analyze`
+pub fn check(ast: &mut AST) -> Result<(), Vec<Error>> {
+    let checker = &mut TypeChecker::default();
+
+    checker.check(ast);
+
+    if checker.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(checker.errors.clone())
+    }
+}
+
+#[cfg(test)]
+fn analyze_text(text: &str) -> Result<Vec<Error>, Vec<Error>> {
+    analyze(&crate::parser::parse(crate::lexer::lex(text).unwrap()).unwrap())
+}
+
+#[test]
+fn analyze_accepts_programs_using_declared_variables() {
+    assert_eq!(analyze_text("PROGRAM p; VAR a, b : INTEGER; BEGIN a := 1; b := a * 2 END."), Ok(Vec::new()));
+}
+
+#[test]
+fn analyze_reports_every_undeclared_variable() {
+    let errors = analyze_text("PROGRAM p; VAR a : INTEGER; BEGIN b := a + c END.").unwrap_err();
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    assert_eq!(messages, ["variable \"b\" is not declared", "variable \"c\" is not declared"]);
+    assert_eq!(errors[0].kind, ErrorKind::Semantic);
+    assert_eq!(errors[1].span, Span::new(43, 44, 1, 44));
+}
+
+#[test]
+fn analyze_reports_duplicate_declarations_with_the_first_declaration() {
+    let errors = analyze_text("PROGRAM p; VAR a : INTEGER; a : REAL; BEGIN END.").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "duplicate declaration of \"a\"");
+    assert_eq!(errors[0].span, Span::new(28, 29, 1, 29));
+    assert_eq!(errors[0].labels[0].span, Span::new(15, 16, 1, 16));
+}
+
+#[test]
+fn analyze_resolves_names_in_enclosing_scopes() {
+    let text = "PROGRAM p; VAR x : INTEGER; PROCEDURE a(y : INTEGER); VAR z : INTEGER; BEGIN z := x + y END; BEGIN x := 1 END.";
+
+    assert_eq!(analyze_text(text), Ok(Vec::new()));
+}
+
+#[test]
+fn analyze_warns_about_shadowed_declarations() {
+    let text = "PROGRAM p; VAR x : INTEGER; PROCEDURE a(x : REAL); BEGIN END; BEGIN END.";
+    let warnings = analyze_text(text).unwrap();
+
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].is_warning());
+    assert_eq!(warnings[0].message, "\"x\" shadows a declaration of scope \"p\" (level 1)");
+    assert_eq!(warnings[0].labels[0].span, Span::new(15, 16, 1, 16));
+}
+
+#[test]
+fn analyze_reports_references_to_variables_of_other_scopes() {
+    let text = "PROGRAM p; PROCEDURE a; VAR y : INTEGER; BEGIN END; BEGIN y := 1 END.";
+    let errors = analyze_text(text).unwrap_err();
+
+    assert_eq!(errors[0].message, "variable \"y\" is out of scope");
+    assert_eq!(errors[0].labels[0].message, "declared in scope \"a\" (level 2)");
+    assert_eq!(errors[0].notes, ["referenced in scope \"p\" (level 1)"]);
+}
+
+#[test]
+fn analyze_reports_procedures_used_as_variables() {
+    let errors = analyze_text("PROGRAM p; PROCEDURE a; BEGIN END; BEGIN a := 1 END.").unwrap_err();
+
+    assert_eq!(errors[0].message, "\"a\" is not a variable");
+    assert_eq!(errors[0].labels[0].message, "declared as procedure here");
+}
+
+#[test]
+fn analyze_checks_procedure_calls() {
+    let text = "PROGRAM p; VAR x : INTEGER; PROCEDURE a(i, j : INTEGER); BEGIN a(i - 1, j) END; BEGIN a(1); b; x(2) END.";
+    let messages: Vec<String> = analyze_text(text).unwrap_err().into_iter().map(|error| error.message).collect();
+
+    assert_eq!(messages, [
+        "procedure \"a\" takes 2 arguments but 1 was given",
+        "procedure \"b\" is not declared",
+        "\"x\" is not a procedure"
+    ]);
+}
+
+#[test]
+fn analyze_accepts_functions_assigning_their_result() {
+    let text = "PROGRAM p; VAR x : INTEGER; FUNCTION f(i : INTEGER) : INTEGER; BEGIN f := i * 2 + f(0) END; BEGIN x := f(2) + 1 END.";
+
+    assert_eq!(analyze_text(text), Ok(Vec::new()));
+}
+
+#[test]
+fn analyze_reports_procedures_called_in_expressions() {
+    let text = "PROGRAM p; VAR x : INTEGER; PROCEDURE a; BEGIN END; BEGIN x := a() + g(1); x := x(1) END.";
+    let messages: Vec<String> = analyze_text(text).unwrap_err().into_iter().map(|error| error.message).collect();
+
+    assert_eq!(messages, [
+        "procedure \"a\" does not return a value",
+        "function \"g\" is not declared",
+        "\"x\" is not a function"
+    ]);
+}
+
+#[test]
+fn analyze_resolves_builtin_functions_unless_shadowed() {
+    let text = "PROGRAM p; VAR s : STRING; n : INTEGER; BEGIN n := length(s) + pos('a', upcase(s)); s := copy(s, 1) END.";
+    let errors = analyze_text(text).unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "built-in function \"copy\" takes 3 arguments but 2 were given");
+    assert_eq!(errors[0].notes, ["its parameters are (STRING, INTEGER, INTEGER)"]);
+
+    let text = "PROGRAM p; VAR n : INTEGER; FUNCTION length(a, b : INTEGER) : INTEGER; BEGIN length := a + b END; BEGIN n := length(1, 2) END.";
+    assert_eq!(analyze_text(text), Ok(Vec::new()));
+
+    let text = "PROGRAM p; VAR s : STRING; n : INTEGER; BEGIN n := LENGTH(s) + Pos('a', UpCase(s)) END.";
+    assert_eq!(analyze_text(text), Ok(Vec::new()));
+}
+
+#[test]
+fn analyze_reports_assignments_to_loop_variables() {
+    let text = "PROGRAM p; VAR i, j : INTEGER; BEGIN FOR i := 1 TO 3 DO BEGIN j := i; i := j + 1; FOR i := 1 TO 2 DO END; i := 0 END.";
+    let errors = analyze_text(text).unwrap_err();
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    assert_eq!(messages, ["cannot assign to loop variable \"i\"", "cannot assign to loop variable \"i\""]);
+    assert_eq!(errors[0].labels[0].span, Span::new(41, 42, 1, 42));
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use crate::ast::Type;
+use crate::builtins::{Builtin, BUILTINS};
+use crate::types::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Symbol {
+    BuiltinType(Type),
+    BuiltinFunction(&'static Builtin),
+    // `span` is where the variable was declared
+    Var { name: String, type_spec: Type, span: Span },
+    // functions have a `return_type`
+    Procedure { name: String, params: Vec<(String, Type)>, return_type: Option<Type>, span: Span }
+}
+
+impl Symbol {
+    // where the symbol was declared, `None` for built-in symbols
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Symbol::BuiltinType(_) | Symbol::BuiltinFunction(_) => None,
+            Symbol::Var { span, .. } | Symbol::Procedure { span, .. } => Some(*span)
+        }
+    }
+
+    // e.g. "variable" in "\"x\" is declared as variable here"
+    pub fn description(&self) -> &'static str {
+        match self {
+            Symbol::BuiltinType(_) => "type",
+            Symbol::BuiltinFunction(_) => "built-in function",
+            Symbol::Var { .. } => "variable",
+            Symbol::Procedure { return_type: Some(_), .. } => "function",
+            Symbol::Procedure { .. } => "procedure"
+        }
+    }
+}
+
+// symbols declared directly in one scope, the `SemanticAnalyzer` chains it to
+// the enclosing scopes, one level further out each
+#[derive(Debug)]
+pub struct ScopedSymbolTable {
+    pub scope_name: String,
+    pub scope_level: usize,
+    symbols: HashMap<String, Symbol>,
+    // names are stored and looked up in lowercase
+    case_insensitive: bool
+}
+
+impl ScopedSymbolTable {
+    pub fn new(scope_name: impl Into<String>, scope_level: usize) -> ScopedSymbolTable {
+        ScopedSymbolTable {
+            scope_name: scope_name.into(),
+            scope_level,
+            symbols: HashMap::new(),
+            case_insensitive: false
+        }
+    }
+
+    // scope of the built-in types and functions, enclosing every program, their
+    // names are case-insensitive like keywords
+    pub fn builtins() -> ScopedSymbolTable {
+        let mut table = ScopedSymbolTable { case_insensitive: true, ..ScopedSymbolTable::new("builtins", 0) };
+
+        table.insert("INTEGER", Symbol::BuiltinType(Type::Integer));
+        table.insert("REAL", Symbol::BuiltinType(Type::Real));
+        table.insert("BOOLEAN", Symbol::BuiltinType(Type::Boolean));
+        table.insert("STRING", Symbol::BuiltinType(Type::String));
+
+        for builtin in BUILTINS {
+            table.insert(builtin.name, Symbol::BuiltinFunction(builtin));
+        }
+
+        table
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, symbol: Symbol) {
+        let name = name.into();
+        let name = if self.case_insensitive { name.to_ascii_lowercase() } else { name };
+
+        self.symbols.insert(name, symbol);
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        if self.case_insensitive {
+            self.symbols.get(&name.to_ascii_lowercase())
+        } else {
+            self.symbols.get(name)
+        }
+    }
+}
+
+#[test]
+fn builtins_declare_the_builtin_types() {
+    let table = ScopedSymbolTable::builtins();
+
+    assert_eq!(table.lookup("INTEGER"), Some(&Symbol::BuiltinType(Type::Integer)));
+    assert_eq!(table.lookup("REAL"), Some(&Symbol::BuiltinType(Type::Real)));
+    assert_eq!(table.lookup("STRING"), Some(&Symbol::BuiltinType(Type::String)));
+    assert!(matches!(table.lookup("length"), Some(Symbol::BuiltinFunction(builtin)) if builtin.return_type == Type::Integer));
+    assert_eq!(table.lookup("x"), None);
+}
+
+#[test]
+fn only_builtins_are_looked_up_case_insensitively() {
+    assert_eq!(ScopedSymbolTable::builtins().lookup("Length"), ScopedSymbolTable::builtins().lookup("length"));
+    assert!(ScopedSymbolTable::builtins().lookup("LENGTH").is_some());
+
+    let mut table = ScopedSymbolTable::new("global", 1);
+    table.insert("x", Symbol::BuiltinType(Type::Integer));
+    assert!(table.lookup("x").is_some());
+    assert_eq!(table.lookup("X"), None);
+}
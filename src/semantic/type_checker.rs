@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::types::*;
+use crate::ast::*;
+use crate::error::*;
+use crate::builtins::{builtin, signature};
+
+// declarations of one program, procedure or function
+#[derive(Debug, Default)]
+struct Scope {
+    variables: HashMap<String, Type>,
+    // parameters and return type of every procedure and function
+    procedures: HashMap<String, (Vec<Param>, Option<Type>)>
+}
+
+// infers the type of every expression, storing it on each `BinaryOperator`,
+// and rejects operands and assignments of the wrong type
+//
+// runs after the `SemanticAnalyzer`, so undeclared variables are already
+// reported and simply have no type here
+#[derive(Debug, Default)]
+pub struct TypeChecker {
+    // declarations per scope, innermost last
+    scopes: Vec<Scope>,
+    pub errors: Vec<Error>
+}
+
+// INTEGER values widen to REAL, nothing converts implicitly the other way
+fn is_assignable(target: Type, value: Type) -> bool {
+    target == value || (target == Type::Real && value == Type::Integer)
+}
+
+fn is_integer_literal(node: &Node) -> bool {
+    matches!(node, Node::Token(Spanned { value: Token::Number(Number::Integer(_)), .. }))
+}
+
+impl TypeChecker {
+    pub fn check(&mut self, ast: &mut AST) {
+        self.visit(&mut ast.root);
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.variables.get(name)).copied()
+    }
+
+    fn lookup_procedure(&self, name: &str) -> Option<&(Vec<Param>, Option<Type>)> {
+        self.scopes.iter().rev().find_map(|scope| scope.procedures.get(name))
+    }
+
+    fn check_condition(&mut self, condition: &mut Node) {
+        if let Some(type_spec) = self.visit(condition).filter(|&type_spec| type_spec != Type::Boolean) {
+            self.errors.push(
+                Error::semantic(format!("mismatched types: expected BOOLEAN, found {}", type_spec), condition.span())
+                    .with_note("conditions have to be BOOLEAN, e.g. `x <> 0` instead of `x`")
+            );
+        }
+    }
+
+    fn check_assignable(&mut self, target: Type, value: Type, expr: &Node, declaration: Span, description: String) {
+        if is_assignable(target, value) {
+            return
+        }
+
+        let mut error = Error::semantic(format!("mismatched types: expected {}, found {}", target, value), expr.span())
+            .with_label(declaration, description);
+
+        if target == Type::Integer && value == Type::Real {
+            error = error.with_note("REAL values are not converted to INTEGER implicitly, `DIV` divides integers");
+        }
+
+        self.errors.push(error);
+    }
+
+    // the type of `node`, `None` for statements and expressions whose type is
+    // unknown because of an error reported before
+    fn visit(&mut self, node: &mut Node) -> Option<Type> {
+        match node {
+            Node::Program(node) => {
+                self.scopes.push(Scope::default());
+                self.visit(&mut node.block);
+                self.scopes.pop();
+
+                None
+            },
+            Node::Block(node) => {
+                for declaration in &mut node.declarations {
+                    self.visit(declaration);
+                }
+
+                self.visit(&mut node.compound_statement)
+            },
+            Node::VarDecl(node) => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.variables.insert(node.name.value.clone(), node.type_spec.value);
+                }
+
+                None
+            },
+            Node::ProcedureDecl(node) => {
+                // not shared yet, only the interpreter shares declarations
+                let node = Arc::make_mut(node);
+                let return_type = node.return_type.map(|type_spec| type_spec.value);
+
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.procedures.insert(node.name.value.clone(), (node.params.clone(), return_type));
+                }
+
+                let mut variables: HashMap<String, Type> = node.params.iter()
+                    .map(|param| (param.name.value.clone(), param.type_spec.value))
+                    .collect();
+
+                // the function's result is assigned to its name
+                if let Some(return_type) = return_type {
+                    variables.insert(node.name.value.clone(), return_type);
+                }
+
+                self.scopes.push(Scope { variables, procedures: HashMap::new() });
+                self.visit(&mut node.block);
+                self.scopes.pop();
+
+                None
+            },
+            Node::ProcedureCall(node) => {
+                let types: Vec<Option<Type>> = node.args.iter_mut().map(|arg| self.visit(arg)).collect();
+                let (params, return_type) = match self.lookup_procedure(&node.name.value).cloned() {
+                    Some(procedure) => procedure,
+                    None => return self.builtin_call(node, types)
+                };
+
+                for ((param, arg), value) in params.iter().zip(&node.args).zip(types) {
+                    if let Some(value) = value {
+                        let description = format!("parameter \"{}\" is declared as {}", param.name.value, param.type_spec.value);
+                        self.check_assignable(param.type_spec.value, value, arg, param.name.span, description);
+                    }
+                }
+
+                return_type
+            },
+            Node::Compound(node) => {
+                for statement in &mut node.value {
+                    self.visit(statement);
+                }
+
+                None
+            },
+            Node::If(node) => {
+                self.check_condition(&mut node.condition);
+                self.visit(&mut node.then_branch);
+
+                if let Some(else_branch) = &mut node.else_branch {
+                    self.visit(else_branch);
+                }
+
+                None
+            },
+            Node::While(node) => {
+                self.check_condition(&mut node.condition);
+                self.visit(&mut node.body);
+
+                None
+            },
+            Node::Repeat(node) => {
+                for statement in &mut node.body {
+                    self.visit(statement);
+                }
+
+                self.check_condition(&mut node.condition);
+
+                None
+            },
+            Node::For(node) => {
+                let variable = self.lookup(&node.variable.value);
+
+                if let Some(type_spec) = variable.filter(|&type_spec| type_spec != Type::Integer) {
+                    self.errors.push(Error::semantic(
+                        format!("loop variable \"{}\" must be INTEGER, found {}", node.variable.value, type_spec),
+                        node.variable.span
+                    ));
+                }
+
+                for bound in [&mut node.start, &mut node.end] {
+                    if let Some(type_spec) = self.visit(bound) {
+                        let description = "loop variables count in INTEGER steps".to_string();
+                        self.check_assignable(Type::Integer, type_spec, bound, node.variable.span, description);
+                    }
+                }
+
+                self.visit(&mut node.body);
+
+                None
+            },
+            Node::NoOp(_) => None,
+            Node::Assign(node) => {
+                let value = self.visit(&mut node.expr);
+
+                if let (Some(target), Some(value)) = (self.lookup(&node.name.value), value) {
+                    let description = format!("\"{}\" is declared as {}", node.name.value, target);
+                    self.check_assignable(target, value, &node.expr, node.name.span, description);
+                }
+
+                None
+            },
+            Node::Var(name) => self.lookup(&name.value),
+            Node::Token(token) => match token.value {
+                Token::Number(Number::MinIntegerMagnitude) => {
+                    self.errors.push(Error::semantic("integer literal out of range", token.span)
+                        .with_note(format!("the largest Integer is {}", i32::MAX)));
+
+                    None
+                },
+                Token::Number(Number::Real(_)) => Some(Type::Real),
+                Token::Number(_) => Some(Type::Integer),
+                Token::TRUE | Token::FALSE => Some(Type::Boolean),
+                Token::String(_) => Some(Type::String),
+                _ => None
+            },
+            Node::UnaryOperator(node) if node.token.value == Token::Operator(Operator::Sub) && node.expr.is_min_integer_magnitude() => {
+                Some(Type::Integer)
+            },
+            Node::UnaryOperator(node) => {
+                let operand = self.visit(&mut node.expr)?;
+
+                if node.token.value == Token::Operator(Operator::Not) {
+                    if operand != Type::Boolean {
+                        self.errors.push(Error::semantic(
+                            format!("operand of Not must be BOOLEAN, found {}", operand),
+                            node.expr.span()
+                        ));
+
+                        return None
+                    }
+
+                    return Some(Type::Boolean)
+                }
+
+                if !operand.is_numeric() {
+                    self.errors.push(Error::semantic(
+                        format!("operand of {} must be numeric, found {}", node.token.value, operand),
+                        node.expr.span()
+                    ));
+
+                    return None
+                }
+
+                Some(operand)
+            },
+            Node::BinaryOperator(node) => {
+                let type_spec = self.binary_operation(node);
+                node.type_spec = type_spec;
+
+                type_spec
+            }
+        }
+    }
+
+    // calls of undeclared procedures are already reported, so only built-ins
+    // are left to check
+    fn builtin_call(&mut self, node: &ProcedureCall, types: Vec<Option<Type>>) -> Option<Type> {
+        let builtin = builtin(&node.name.value)?;
+
+        for ((&param, arg), value) in builtin.params.iter().zip(&node.args).zip(types) {
+            if let Some(value) = value.filter(|&value| !is_assignable(param, value)) {
+                self.errors.push(
+                    Error::semantic(format!("mismatched types: expected {}, found {}", param, value), arg.span())
+                        .with_note(format!("built-in function \"{}\" takes ({})", builtin.name, signature(builtin.params)))
+                );
+            }
+        }
+
+        Some(builtin.return_type)
+    }
+
+    fn binary_operation(&mut self, node: &mut BinaryOperator) -> Option<Type> {
+        let left = self.visit(&mut node.left);
+        let right = self.visit(&mut node.right);
+        let operator = match node.token.value {
+            Token::Operator(operator) => operator,
+            _ => return None
+        };
+
+        if operator.is_logical() {
+            for (operand, type_spec) in [(&node.left, left), (&node.right, right)] {
+                if let Some(type_spec) = type_spec.filter(|&type_spec| type_spec != Type::Boolean) {
+                    self.errors.push(
+                        Error::semantic(format!("operands of {} must be BOOLEAN", operator), node.token.span)
+                            .with_label(operand.span(), format!("this is {}", type_spec))
+                            .with_note("AND and OR bind tighter than comparisons, e.g. write `(a < b) AND (c < d)`")
+                    );
+
+                    return None
+                }
+            }
+
+            return Some(Type::Boolean)
+        }
+
+        if operator.is_comparison() {
+            if let (Some(left), Some(right)) = (left, right) {
+                if !(left.is_numeric() && right.is_numeric()) && left != right {
+                    self.errors.push(
+                        Error::semantic(format!("cannot compare {} with {}", left, right), node.token.span)
+                            .with_label(node.left.span(), format!("this is {}", left))
+                            .with_label(node.right.span(), format!("this is {}", right))
+                    );
+
+                    return None
+                }
+            }
+
+            return Some(Type::Boolean)
+        }
+
+        if operator == Operator::Add && (left == Some(Type::String) || right == Some(Type::String)) {
+            let (left, right) = (left?, right?);
+
+            if left != right {
+                self.errors.push(
+                    Error::semantic(format!("cannot add {} to {}", right, left), node.token.span)
+                        .with_label(node.left.span(), format!("this is {}", left))
+                        .with_label(node.right.span(), format!("this is {}", right))
+                        .with_note("`+` only joins a STRING with another STRING")
+                );
+
+                return None
+            }
+
+            return Some(Type::String)
+        }
+
+        for (operand, type_spec) in [(&node.left, left), (&node.right, right)] {
+            if let Some(type_spec) = type_spec.filter(|type_spec| !type_spec.is_numeric()) {
+                self.errors.push(
+                    Error::semantic(format!("operands of {} must be numeric", operator), node.token.span)
+                        .with_label(operand.span(), format!("this is {}", type_spec))
+                );
+
+                return None
+            }
+        }
+
+        let (left, right) = (left?, right?);
+        let integers = left == Type::Integer && right == Type::Integer;
+
+        match operator {
+            Operator::Add | Operator::Sub | Operator::Mul if integers => Some(Type::Integer),
+            Operator::Add | Operator::Sub | Operator::Mul | Operator::Div => Some(Type::Real),
+            Operator::IntDiv | Operator::Mod => {
+                let (operand, type_spec) = if left != Type::Integer { (&node.left, left) } else { (&node.right, right) };
+
+                if !integers {
+                    self.errors.push(
+                        Error::semantic(format!("operands of {} must be INTEGER", operator), node.token.span)
+                            .with_label(operand.span(), format!("this is {}", type_spec))
+                    );
+                }
+
+                Some(Type::Integer)
+            },
+            // only a literal exponent is known not to be negative, which would
+            // make the result a fraction
+            Operator::Pow if integers && is_integer_literal(&node.right) => Some(Type::Integer),
+            Operator::Pow => Some(Type::Real),
+            operator => unreachable!("{} is not an arithmetic operator", operator)
+        }
+    }
+}
+
+#[cfg(test)]
+fn check_text(text: &str) -> (AST, Vec<Error>) {
+    let mut ast = crate::parser::parse(crate::lexer::lex(text).unwrap()).unwrap();
+    let checker = &mut TypeChecker::default();
+
+    checker.check(&mut ast);
+
+    (ast, checker.errors.clone())
+}
+
+// the `BinaryOperator` assigned in the first statement of a program
+#[cfg(test)]
+fn first_assigned_type(ast: &AST) -> Option<Type> {
+    let block = match &ast.root {
+        Node::Program(program) => match &program.block {
+            Node::Block(block) => block,
+            _ => return None
+        },
+        _ => return None
+    };
+
+    match &block.compound_statement {
+        Node::Compound(compound) => match compound.value.first() {
+            Some(Node::Assign(assign)) => match &assign.expr {
+                Node::BinaryOperator(node) => node.type_spec,
+                _ => None
+            },
+            _ => None
+        },
+        _ => None
+    }
+}
+
+#[test]
+fn check_infers_the_type_of_binary_operators() {
+    let (ast, errors) = check_text("PROGRAM p; VAR a : INTEGER; BEGIN a := a * 2 + 1 END.");
+    assert!(errors.is_empty());
+    assert_eq!(first_assigned_type(&ast), Some(Type::Integer));
+
+    let (ast, _) = check_text("PROGRAM p; VAR a : INTEGER; x : REAL; BEGIN x := a * 2.5 END.");
+    assert_eq!(first_assigned_type(&ast), Some(Type::Real));
+
+    let (ast, _) = check_text("PROGRAM p; VAR a : INTEGER; x : REAL; BEGIN x := a / 2 END.");
+    assert_eq!(first_assigned_type(&ast), Some(Type::Real));
+}
+
+#[cfg(not(feature = "bignum"))]
+#[test]
+fn check_accepts_the_digits_of_the_smallest_integer_only_negated() {
+    let (_, errors) = check_text("PROGRAM p; VAR a : INTEGER; BEGIN a := -2147483648; a := -2147483648 ** 2 END.");
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "integer literal out of range");
+    assert_eq!(errors[0].span, Span::new(58, 68, 1, 59));
+}
+
+#[test]
+fn check_widens_integers_assigned_to_reals() {
+    let (_, errors) = check_text("PROGRAM p; VAR a : INTEGER; x : REAL; BEGIN x := a DIV 2 END.");
+
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn check_rejects_reals_assigned_to_integers() {
+    let (_, errors) = check_text("PROGRAM p; VAR a : INTEGER; BEGIN a := a / 2 END.");
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ErrorKind::Semantic);
+    assert_eq!(errors[0].message, "mismatched types: expected INTEGER, found REAL");
+    assert_eq!(errors[0].span, Span::new(39, 44, 1, 40));
+    assert_eq!(errors[0].labels[0].message, "\"a\" is declared as INTEGER");
+}
+
+#[test]
+fn check_rejects_booleans_in_arithmetic_and_reals_in_integer_division() {
+    let (_, errors) = check_text("PROGRAM p; VAR a : INTEGER; b : BOOLEAN; BEGIN a := b + 1; a := 2.5 % 2 END.");
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    assert_eq!(messages, ["operands of Add must be numeric", "operands of Mod must be INTEGER"]);
+    assert_eq!(errors[1].labels[0].message, "this is REAL");
+}
+
+#[test]
+fn check_types_powers_with_unknown_exponents_as_real() {
+    let (ast, _) = check_text("PROGRAM p; VAR a : INTEGER; BEGIN a := 2 ** 3 END.");
+    assert_eq!(first_assigned_type(&ast), Some(Type::Integer));
+
+    let (ast, _) = check_text("PROGRAM p; VAR a : INTEGER; x : REAL; BEGIN x := 2 ** a END.");
+    assert_eq!(first_assigned_type(&ast), Some(Type::Real));
+}
+
+#[test]
+fn check_rejects_reals_passed_for_integer_parameters() {
+    let (_, errors) = check_text("PROGRAM p; PROCEDURE a(i : INTEGER; x : REAL); BEGIN END; BEGIN a(1, 2); a(2.5, 1) END.");
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "mismatched types: expected INTEGER, found REAL");
+    assert_eq!(errors[0].labels[0].message, "parameter \"i\" is declared as INTEGER");
+}
+
+#[test]
+fn check_types_function_calls_by_their_return_type() {
+    let (_, errors) = check_text("PROGRAM p; VAR a : INTEGER; FUNCTION half(i : INTEGER) : REAL; BEGIN half := i / 2 END; BEGIN a := half(3) END.");
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "mismatched types: expected INTEGER, found REAL");
+
+    let (ast, errors) = check_text("PROGRAM p; VAR a : INTEGER; FUNCTION f : INTEGER; BEGIN f := 2 END; BEGIN a := f() * 2 END.");
+    assert!(errors.is_empty());
+    assert_eq!(first_assigned_type(&ast), Some(Type::Integer));
+}
+
+#[test]
+fn check_types_comparisons_and_logical_operators_as_boolean() {
+    let (_, errors) = check_text("PROGRAM p; VAR a : INTEGER; b : BOOLEAN; BEGIN b := (a < 2.5) AND NOT (a = 1) OR TRUE END.");
+    assert!(errors.is_empty());
+
+    let (_, errors) = check_text("PROGRAM p; VAR a : INTEGER; b : BOOLEAN; BEGIN b := a < 1 AND b; a := b = 1; a := a = 1; b := NOT a END.");
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    assert_eq!(messages, [
+        "operands of And must be BOOLEAN",
+        "cannot compare BOOLEAN with INTEGER",
+        "mismatched types: expected INTEGER, found BOOLEAN",
+        "operand of Not must be BOOLEAN, found INTEGER"
+    ]);
+}
+
+#[test]
+fn check_types_strings_and_builtin_calls() {
+    let (ast, errors) = check_text("PROGRAM p; VAR s : STRING; b : BOOLEAN; BEGIN s := 'a' + upcase(s); b := s < 'b'; b := pos('a', s) > length(s) END.");
+    assert!(errors.is_empty());
+    assert_eq!(first_assigned_type(&ast), Some(Type::String));
+
+    let (_, errors) = check_text("PROGRAM p; VAR s : STRING; n : INTEGER; BEGIN s := s + 1; n := s; s := copy(s, 1.5, 2); n := length(s) = s END.");
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    assert_eq!(messages, [
+        "cannot add INTEGER to STRING",
+        "mismatched types: expected INTEGER, found STRING",
+        "mismatched types: expected INTEGER, found REAL",
+        "cannot compare INTEGER with STRING"
+    ]);
+    assert_eq!(errors[2].notes, ["built-in function \"copy\" takes (STRING, INTEGER, INTEGER)"]);
+}
+
+#[test]
+fn check_rejects_conditions_that_are_not_boolean() {
+    let (_, errors) = check_text("PROGRAM p; VAR a : INTEGER; BEGIN IF a THEN a := 1 ELSE IF a > 1 THEN a := 2.5 END.");
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    assert_eq!(messages, ["mismatched types: expected BOOLEAN, found INTEGER", "mismatched types: expected INTEGER, found REAL"]);
+}
+
+#[test]
+fn check_requires_integer_loop_variables_and_boolean_loop_conditions() {
+    let (_, errors) = check_text("PROGRAM p; VAR i : INTEGER; x : REAL; \
+        BEGIN FOR x := 1 TO 2 DO; FOR i := 1 TO x DO; WHILE i DO; REPEAT UNTIL i > 1 END.");
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    assert_eq!(messages, [
+        "loop variable \"x\" must be INTEGER, found REAL",
+        "mismatched types: expected INTEGER, found REAL",
+        "mismatched types: expected BOOLEAN, found INTEGER"
+    ]);
+}
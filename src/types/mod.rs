@@ -1,12 +1,105 @@
 use std::fmt;
 
+// byte offsets are half-open, line and column are 1-based and point at `start`
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span { start, end, line, column }
+    }
+
+    // smallest span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Span {
+        let (first, last) = if self.start <= other.start { (self, other) } else { (other, self) };
+
+        Span {
+            start: first.start,
+            end: std::cmp::max(first.end, last.end),
+            line: first.line,
+            column: first.column
+        }
+    }
+}
+
+impl Default for Span {
+    fn default() -> Span {
+        Span::new(0, 0, 1, 1)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[test]
+fn span_to_covers_both_spans() {
+    let left = Span::new(0, 1, 1, 1);
+    let right = Span::new(4, 7, 1, 5);
+
+    assert_eq!(left.to(right), Span::new(0, 7, 1, 1));
+    assert_eq!(right.to(left), Span::new(0, 7, 1, 1));
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span
+}
+
+impl<T> Spanned<T> {
+    pub fn new(value: T, span: Span) -> Spanned<T> {
+        Spanned { value, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Number(Number),
     Operator(Operator),
+    // name of a variable
+    Id(String),
+    // contents of a string literal, with `''` already unescaped
+    String(String),
+    // `:=`
+    ASSIGN,
+    PROGRAM,
+    VAR,
+    PROCEDURE,
+    FUNCTION,
+    INTEGER,
+    REAL,
+    BOOLEAN,
+    STRING,
+    TRUE,
+    FALSE,
+    BEGIN,
+    END,
+    IF,
+    THEN,
+    ELSE,
+    WHILE,
+    DO,
+    REPEAT,
+    UNTIL,
+    FOR,
+    TO,
+    DOWNTO,
+    SEMI,
+    DOT,
+    COLON,
+    COMMA,
     Whitespace,
     LPAREN,
     RPAREN,
+    EOF,
     Unknown
 }
 
@@ -15,49 +108,71 @@ pub enum Operator {
     Add,
     Sub,
     Mul,
-    Div
+    // always divides as Real (or Rational in exact division mode)
+    Div,
+    // integer division truncating towards zero, `DIV` or `//`
+    IntDiv,
+    // remainder of `IntDiv`, takes the sign of the dividend
+    Mod,
+    // `**` or `^`
+    Pow,
+    // `=`
+    Equal,
+    // `<>`
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+    // prefix only
+    Not
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+impl Operator {
+    // compares two numbers or two booleans, resulting in a boolean
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Operator::Equal | Operator::NotEqual | Operator::Less | Operator::LessEqual |
+            Operator::Greater | Operator::GreaterEqual
+        )
+    }
+
+    // takes booleans, resulting in a boolean
+    pub fn is_logical(self) -> bool {
+        matches!(self, Operator::And | Operator::Or | Operator::Not)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Number {
-    Integer(i32)
+    Integer(i32),
+    // integer literal that does not fit into an Integer
+    #[cfg(feature = "bignum")]
+    BigInteger(num_bigint::BigInt),
+    // `2147483648` without `bignum`, only an Integer once negated, so that
+    // `-2147483648` can be written
+    MinIntegerMagnitude,
+    Real(f64)
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            _ => f.write_str(&format!("{:?}", self))
-        }
+        write!(f, "{:?}", self)
     }
 }
 
 impl fmt::Display for Operator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            _ => f.write_str(&format!("{:?}", self))
-        }
-    }
-}
-
-pub fn is_addsub_operator(token: Token) -> bool {
-    match token {
-        Token::Operator(Operator::Add) => true,
-        Token::Operator(Operator::Sub) => true,
-        _ => false
+        write!(f, "{:?}", self)
     }
 }
 
-pub fn is_muldiv_operator(token: Token) -> bool {
-    match token {
-        Token::Operator(Operator::Mul) => true,
-        Token::Operator(Operator::Div) => true,
-        _ => false
-    }
-}
-
-pub fn is_whitespace(token: Token) -> bool {
+pub fn is_whitespace(token: &Token) -> bool {
     if let Token::Whitespace = token {
         return true
     }
     false
-}
\ No newline at end of file
+}
@@ -0,0 +1,241 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::convert::TryFrom;
+
+use crate::ast::Type;
+
+mod rational;
+
+pub use self::rational::Rational;
+
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+#[cfg(feature = "bignum")]
+use num_traits::{ToPrimitive, Zero};
+
+// result of evaluating an expression
+//
+// arithmetic between two Integers stays Integer (except for `/`, which divides
+// as Real, or as Rational in exact division mode), as soon as one operand is
+// Real the other is promoted to Real; Strings only mix with other Strings
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i32),
+    // only holds integers outside of the Integer range, see `Value::big_integer`
+    #[cfg(feature = "bignum")]
+    BigInteger(BigInt),
+    // only holds fractions that are not whole numbers, see `Value::rational`
+    Rational(Rational),
+    Real(f64),
+    Boolean(bool),
+    String(String)
+}
+
+impl Value {
+    // normalizes to `Value::Integer` whenever the value fits, so equal numbers
+    // always compare equal
+    #[cfg(feature = "bignum")]
+    pub fn big_integer(value: BigInt) -> Value {
+        match value.to_i32() {
+            Some(value) => Value::Integer(value),
+            None => Value::BigInteger(value)
+        }
+    }
+
+    #[cfg(feature = "bignum")]
+    pub fn to_big_integer(&self) -> Option<BigInt> {
+        match self {
+            Value::Integer(value) => Some(BigInt::from(*value)),
+            Value::BigInteger(value) => Some(value.clone()),
+            Value::Rational(_) | Value::Real(_) | Value::Boolean(_) | Value::String(_) => None
+        }
+    }
+
+    // normalizes whole numbers to an integer value, like `Value::big_integer`
+    pub fn rational(value: Rational) -> Value {
+        if !value.is_integer() {
+            return Value::Rational(value)
+        }
+
+        match i32::try_from(value.numerator()) {
+            Ok(value) => Value::Integer(value),
+            #[cfg(feature = "bignum")]
+            Err(_) => Value::BigInteger(BigInt::from(value.numerator())),
+            #[cfg(not(feature = "bignum"))]
+            Err(_) => Value::Rational(value)
+        }
+    }
+
+    // `None` for Reals and integers too large for a Rational
+    pub fn to_rational(&self) -> Option<Rational> {
+        match self {
+            Value::Integer(value) => Some(Rational::from_integer(i64::from(*value))),
+            #[cfg(feature = "bignum")]
+            Value::BigInteger(value) => value.to_i64().map(Rational::from_integer),
+            Value::Rational(value) => Some(*value),
+            Value::Real(_) | Value::Boolean(_) | Value::String(_) => None
+        }
+    }
+
+    // Rationals as their (possibly inexact) Real approximation, other values unchanged
+    pub fn to_decimal(&self) -> Value {
+        match self {
+            Value::Rational(value) => Value::Real(value.to_f64()),
+            value => value.clone()
+        }
+    }
+
+    // Integers and BigIntegers, but not whole Reals
+    pub fn is_integer(&self) -> bool {
+        match self {
+            Value::Integer(_) => true,
+            #[cfg(feature = "bignum")]
+            Value::BigInteger(_) => true,
+            Value::Rational(_) | Value::Real(_) | Value::Boolean(_) | Value::String(_) => false
+        }
+    }
+
+    // Integers and Rationals are exact, Reals are not
+    pub fn is_exact(&self) -> bool {
+        self.is_numeric() && !matches!(self, Value::Real(_))
+    }
+
+    // the declared type of variables that can hold this value
+    pub fn type_spec(&self) -> Type {
+        match self {
+            Value::Rational(_) | Value::Real(_) => Type::Real,
+            Value::Boolean(_) => Type::Boolean,
+            Value::String(_) => Type::String,
+            _ => Type::Integer
+        }
+    }
+
+    // bytes of the text of a String or the digits of a BigInteger, the other
+    // values have a fixed size and take none
+    pub fn size(&self) -> usize {
+        match self {
+            Value::String(value) => value.len(),
+            #[cfg(feature = "bignum")]
+            Value::BigInteger(value) => usize::try_from(value.bits().div_ceil(8)).unwrap_or(usize::MAX),
+            _ => 0
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        !matches!(self, Value::Boolean(_) | Value::String(_))
+    }
+
+    pub fn to_real(&self) -> f64 {
+        match self {
+            Value::Integer(value) => f64::from(*value),
+            #[cfg(feature = "bignum")]
+            Value::BigInteger(value) => value.to_f64().unwrap_or(f64::NAN),
+            Value::Rational(value) => value.to_f64(),
+            Value::Real(value) => *value,
+            Value::Boolean(_) | Value::String(_) => f64::NAN
+        }
+    }
+
+    // numbers compare by their value regardless of their variant, so `1 = 1.0`,
+    // strings compare character by character; `None` for values of different
+    // kinds, and for NaN
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Boolean(left), Value::Boolean(right)) => Some(left.cmp(right)),
+            (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+            (left, right) if !left.is_numeric() || !right.is_numeric() => None,
+            #[cfg(feature = "bignum")]
+            (left, right) if left.is_integer() && right.is_integer() => Some(left.to_big_integer()?.cmp(&right.to_big_integer()?)),
+            (left, right) if left.is_exact() && right.is_exact() => match (left.to_rational(), right.to_rational()) {
+                (Some(left), Some(right)) => Some(left.cmp(&right)),
+                _ => left.to_real().partial_cmp(&right.to_real())
+            },
+            (left, right) => left.to_real().partial_cmp(&right.to_real())
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Value::Integer(value) => *value == 0,
+            #[cfg(feature = "bignum")]
+            Value::BigInteger(value) => value.is_zero(),
+            Value::Rational(value) => value.numerator() == 0,
+            Value::Real(value) => *value == 0.0,
+            Value::Boolean(_) | Value::String(_) => false
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            #[cfg(feature = "bignum")]
+            Value::BigInteger(value) => write!(f, "{}", value),
+            Value::Rational(value) => write!(f, "{}", value),
+            // Debug keeps the trailing `.0` of integral reals, so `2.0` does not print as `2`
+            Value::Real(value) => write!(f, "{:?}", value),
+            // spelled like the literals
+            Value::Boolean(true) => f.write_str("TRUE"),
+            Value::Boolean(false) => f.write_str("FALSE"),
+            // the text itself, without quotes
+            Value::String(value) => f.write_str(value)
+        }
+    }
+}
+
+#[test]
+fn integers_are_promoted_to_real() {
+    assert_eq!(Value::Integer(3).to_real(), 3.0);
+    assert_eq!(Value::Real(1.5).to_real(), 1.5);
+}
+
+#[test]
+fn display_distinguishes_integers_and_reals() {
+    assert_eq!(Value::Integer(2).to_string(), "2");
+    assert_eq!(Value::Real(2.0).to_string(), "2.0");
+    assert_eq!(Value::Real(0.0015).to_string(), "0.0015");
+}
+
+#[test]
+fn compare_orders_numbers_of_different_variants() {
+    let half = Value::rational(Rational::new(1, 2).unwrap());
+
+    assert_eq!(Value::Integer(1).compare(&Value::Real(1.0)), Some(Ordering::Equal));
+    assert_eq!(half.compare(&Value::Integer(1)), Some(Ordering::Less));
+    assert_eq!(half.compare(&Value::Real(0.25)), Some(Ordering::Greater));
+    assert_eq!(Value::Real(f64::NAN).compare(&Value::Real(f64::NAN)), None);
+    assert_eq!(Value::Boolean(true).compare(&Value::Integer(1)), None);
+}
+
+#[test]
+fn compare_orders_strings_and_keeps_them_apart_from_numbers() {
+    let string = |value: &str| Value::String(value.to_string());
+
+    assert_eq!(string("abc").compare(&string("abd")), Some(Ordering::Less));
+    assert_eq!(string("ab").compare(&string("a")), Some(Ordering::Greater));
+    assert_eq!(string("1").compare(&Value::Integer(1)), None);
+    assert!(!string("1").is_numeric());
+}
+
+#[test]
+fn whole_rationals_are_normalized_to_integer() {
+    assert_eq!(Value::rational(Rational::new(4, 2).unwrap()), Value::Integer(2));
+    assert_eq!(Value::rational(Rational::new(1, 2).unwrap()), Value::Rational(Rational::new(1, 2).unwrap()));
+}
+
+#[test]
+fn to_decimal_approximates_rationals() {
+    assert_eq!(Value::rational(Rational::new(1, 4).unwrap()).to_decimal(), Value::Real(0.25));
+    assert_eq!(Value::Integer(3).to_decimal(), Value::Integer(3));
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn big_integers_that_fit_are_normalized_to_integer() {
+    assert_eq!(Value::big_integer(BigInt::from(42)), Value::Integer(42));
+    assert_eq!(
+        Value::big_integer(BigInt::from(i64::MAX)),
+        Value::BigInteger(BigInt::from(i64::MAX))
+    );
+}
@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::convert::TryFrom;
+
+// exact fraction, always stored reduced and with a positive denominator
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rational {
+    numerator: i64,
+    denominator: i64
+}
+
+fn gcd(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+
+    a
+}
+
+impl Rational {
+    // `None` if the denominator is zero or the fraction cannot be represented
+    pub fn new(numerator: i64, denominator: i64) -> Option<Rational> {
+        if denominator == 0 {
+            return None
+        }
+
+        let divisor = i64::try_from(gcd(numerator, denominator)).ok()?;
+        let sign = if denominator < 0 { -1 } else { 1 };
+
+        Some(Rational {
+            numerator: (numerator / divisor).checked_mul(sign)?,
+            denominator: (denominator / divisor).checked_mul(sign)?
+        })
+    }
+
+    pub fn from_integer(value: i64) -> Rational {
+        Rational { numerator: value, denominator: 1 }
+    }
+
+    pub fn numerator(self) -> i64 {
+        self.numerator
+    }
+
+    pub fn denominator(self) -> i64 {
+        self.denominator
+    }
+
+    pub fn is_integer(self) -> bool {
+        self.denominator == 1
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    pub fn checked_neg(self) -> Option<Rational> {
+        Some(Rational { numerator: self.numerator.checked_neg()?, denominator: self.denominator })
+    }
+
+    pub fn checked_add(self, other: Rational) -> Option<Rational> {
+        Rational::new(
+            self.numerator.checked_mul(other.denominator)?.checked_add(other.numerator.checked_mul(self.denominator)?)?,
+            self.denominator.checked_mul(other.denominator)?
+        )
+    }
+
+    pub fn checked_sub(self, other: Rational) -> Option<Rational> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    pub fn checked_mul(self, other: Rational) -> Option<Rational> {
+        Rational::new(
+            self.numerator.checked_mul(other.numerator)?,
+            self.denominator.checked_mul(other.denominator)?
+        )
+    }
+
+    pub fn checked_div(self, other: Rational) -> Option<Rational> {
+        Rational::new(
+            self.numerator.checked_mul(other.denominator)?,
+            self.denominator.checked_mul(other.numerator)?
+        )
+    }
+
+    // `None` on overflow or for a negative power of zero
+    pub fn checked_pow(self, exponent: i32) -> Option<Rational> {
+        let base = if exponent < 0 { Rational::new(self.denominator, self.numerator)? } else { self };
+        let exponent = exponent.unsigned_abs();
+
+        // powers of coprime numbers stay coprime, so the result is still reduced
+        Some(Rational {
+            numerator: base.numerator.checked_pow(exponent)?,
+            denominator: base.denominator.checked_pow(exponent)?
+        })
+    }
+}
+
+// the denominators are positive, so cross-multiplying keeps the order
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        let left = i128::from(self.numerator) * i128::from(other.denominator);
+        let right = i128::from(other.numerator) * i128::from(self.denominator);
+
+        left.cmp(&right)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+#[test]
+fn new_reduces_the_fraction_and_normalizes_the_sign() {
+    assert_eq!(Rational::new(2, 4), Rational::new(1, 2));
+    assert_eq!(Rational::new(3, -6), Rational::new(-1, 2));
+    assert_eq!(Rational::new(3, -6).unwrap().denominator(), 2);
+    assert_eq!(Rational::new(1, 0), None);
+}
+
+#[test]
+fn arithmetic_is_exact() {
+    let third = Rational::new(1, 3).unwrap();
+    let sixth = Rational::new(1, 6).unwrap();
+
+    assert_eq!(third.checked_add(sixth), Rational::new(1, 2));
+    assert_eq!(third.checked_sub(sixth), Rational::new(1, 6));
+    assert_eq!(third.checked_mul(sixth), Rational::new(1, 18));
+    assert_eq!(third.checked_div(sixth), Rational::new(2, 1));
+}
+
+#[test]
+fn pow_inverts_the_fraction_for_negative_exponents() {
+    let two_thirds = Rational::new(-2, 3).unwrap();
+
+    assert_eq!(two_thirds.checked_pow(3), Rational::new(-8, 27));
+    assert_eq!(two_thirds.checked_pow(-2), Rational::new(9, 4));
+    assert_eq!(two_thirds.checked_pow(0), Rational::new(1, 1));
+    assert_eq!(Rational::from_integer(0).checked_pow(-1), None);
+}
+
+#[test]
+fn arithmetic_reports_overflow() {
+    let big = Rational::from_integer(i64::MAX);
+
+    assert_eq!(big.checked_mul(Rational::from_integer(2)), None);
+    assert_eq!(Rational::from_integer(i64::MIN).checked_neg(), None);
+}
+
+#[test]
+fn display_prints_a_reduced_fraction() {
+    assert_eq!(Rational::new(-2, 6).unwrap().to_string(), "-1/3");
+    assert_eq!(Rational::new(4, 2).unwrap().to_string(), "2");
+}
+
+#[test]
+fn ordering_compares_the_fractions() {
+    assert!(Rational::new(1, 3).unwrap() < Rational::new(1, 2).unwrap());
+    assert!(Rational::new(-1, 2).unwrap() < Rational::from_integer(0));
+    assert!(Rational::new(i64::MAX, 2).unwrap() > Rational::new(i64::MAX - 2, 2).unwrap());
+}